@@ -52,5 +52,56 @@ fn bench(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench);
+fn bench_transcoding(c: &mut Criterion) {
+    // 64 MB of words in random order, so that the branches of scalar decoding are not predictable
+    let size = 64_000_000;
+    let texts = [
+        ("ascii", "The quick brown fox jumps over the lazy dog. "),
+        ("latin", "Grüße aus Köln, schöne Straßen und Plätze am Rhein. "),
+        ("cyrillic", "Привет, мир! Съешь же ещё этих мягких французских булок. "),
+        ("cjk", "你好，世界！敏捷的棕色狐狸跳过了懒狗。"),
+    ];
+
+    let mut group = c.benchmark_group("transcoding");
+    group.sample_size(10);
+
+    for (name, text) in texts {
+        let words = text.split_inclusive([' ', '，', '的']).collect::<Vec<_>>();
+
+        // xorshift
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut data = String::with_capacity(size);
+        while data.len() < size {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            data.push_str(words[(state % words.len() as u64) as usize]);
+        }
+
+        let mut utf16 = vec![0u16; data.len()];
+        group.throughput(criterion::Throughput::BytesDecimal(data.len() as u64));
+
+        // utf8simd
+        group.bench_function(format!("utf8simd/{name}"), |b| {
+            b.iter(|| utf8simd::utf8_to_utf16(black_box(data.as_bytes()), &mut utf16).unwrap())
+        });
+
+        // core
+        group.bench_function(format!("core/{name}"), |b| {
+            b.iter(|| {
+                let str = core::str::from_utf8(black_box(data.as_bytes())).unwrap();
+                let mut len = 0;
+                for (unit, dst) in str.encode_utf16().zip(&mut utf16) {
+                    *dst = unit;
+                    len += 1;
+                }
+                len
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench, bench_transcoding);
 criterion_main!(benches);
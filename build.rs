@@ -0,0 +1,47 @@
+//! Derives the backend selection from the target and the enabled features.
+//!
+//! The conditions are evaluated once here and passed to the crate as `cfg`
+//! flags, instead of repeating the same `cfg(any(...))` expressions in every
+//! module that depends on them:
+//!
+//! - `utf8simd_dispatch`: kernels are selected at runtime on x86
+//! - `utf8simd_avx2`, `utf8simd_avx512`: the 32-byte and 64-byte vector types
+//!   are compiled, because they are the widest enabled by the target features
+//!   or for dispatch
+//! - `utf8simd_portable`, `utf8simd_scalar`: the target has no `core::arch`
+//!   backend and uses portable SIMD on nightly or plain arrays on stable
+
+use std::env;
+
+fn main() {
+    println!("cargo::rerun-if-changed=build.rs");
+
+    for cfg in ["utf8simd_dispatch", "utf8simd_avx2", "utf8simd_avx512", "utf8simd_portable", "utf8simd_scalar"] {
+        println!("cargo::rustc-check-cfg=cfg({cfg})");
+    }
+
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let target_features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    let target_feature = |name: &str| target_features.split(',').any(|feature| feature == name);
+    let feature = |name: &str| env::var_os(format!("CARGO_FEATURE_{}", name.to_uppercase())).is_some();
+
+    let x86 = arch == "x86" || arch == "x86_64";
+    let native = x86 || arch == "aarch64" || (arch == "wasm32" && target_feature("simd128"));
+    let dispatch = x86 && feature("std") && !feature("swar");
+    let avx512 = x86 && target_feature("avx512bw") && target_feature("avx512vbmi");
+
+    let cfgs = [
+        ("utf8simd_dispatch", dispatch),
+        // without dispatch, only the widest enabled vector type is used
+        ("utf8simd_avx2", x86 && ((target_feature("avx2") && !avx512) || dispatch)),
+        ("utf8simd_avx512", avx512 || (x86 && dispatch)),
+        ("utf8simd_portable", !native && feature("nightly")),
+        ("utf8simd_scalar", !native && !feature("nightly")),
+    ];
+
+    for (cfg, enabled) in cfgs {
+        if enabled {
+            println!("cargo::rustc-cfg={cfg}");
+        }
+    }
+}
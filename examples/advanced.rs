@@ -1,11 +1,9 @@
-#![feature(portable_simd)]
-
-use core::simd::Simd;
-
 use utf8simd::Utf8Validator;
 
 fn main() -> utf8simd::Result<()> {
-    let data = Simd::load_or_default(b"hello world!");
+    // chunks are padded with zeros
+    let mut data = [0u8; 64];
+    data[..12].copy_from_slice(b"hello world!");
 
     let mut validator = Utf8Validator::default();
     validator.next(&data)?;
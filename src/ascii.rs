@@ -0,0 +1,143 @@
+use crate::simd::Vector;
+use crate::validator;
+use crate::{AsciiError, from_utf8_unchecked};
+
+/// Checks that all bytes of a slice are ASCII.
+///
+/// Faster and stricter than validating the slice as UTF-8, see
+/// [`first_non_ascii`].
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(utf8simd::is_ascii(b"Content-Type: text/plain"));
+/// assert!(!utf8simd::is_ascii("Grüße".as_bytes()));
+/// ```
+pub fn is_ascii(v: &[u8]) -> bool {
+    first_non_ascii(v).is_none()
+}
+
+/// Returns the index of the first byte of a slice that is not ASCII.
+///
+/// Like [`from_utf8`](crate::from_utf8), the slice is checked in aligned
+/// 64-byte chunks with the widest SIMD instructions of the CPU, the unaligned
+/// prefix and the remainder are padded with zeros. Only the chunk with the
+/// first non-ASCII byte is searched byte by byte.
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(utf8simd::first_non_ascii("Grüße".as_bytes()), Some(2));
+/// assert_eq!(utf8simd::first_non_ascii(b"Gruesse"), None);
+/// ```
+pub fn first_non_ascii(v: &[u8]) -> Option<usize> {
+    // not worth it to use SIMD
+    if v.len() < 128 {
+        return position(v);
+    }
+
+    #[cfg(utf8simd_dispatch)]
+    return crate::dispatch::first_non_ascii(v);
+
+    #[cfg(not(utf8simd_dispatch))]
+    first_non_ascii_simd::<crate::simd::Native>(v)
+}
+
+/// Converts a slice of bytes to a string slice if all bytes are ASCII.
+///
+/// # Errors
+/// Fails at the first byte above 0x7F, see [`AsciiError`].
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(utf8simd::from_ascii(b"GET / HTTP/1.1"), Ok("GET / HTTP/1.1"));
+/// assert_eq!(utf8simd::from_ascii("Grüße".as_bytes()).unwrap_err().valid_up_to(), 2);
+/// ```
+pub fn from_ascii(v: &[u8]) -> Result<&str, AsciiError> {
+    match first_non_ascii(v) {
+        Some(index) => Err(AsciiError::new(index)),
+        // ASCII is valid UTF-8
+        None => Ok(unsafe { from_utf8_unchecked(v) }),
+    }
+}
+
+/// Searches a slice in 64-byte chunks with vectors of type `V`.
+///
+/// Always inlined, see [`Vector`].
+#[inline(always)]
+pub(crate) fn first_non_ascii_simd<V: Vector>(v: &[u8]) -> Option<usize> {
+    // alignment offset for 64-byte boundary
+    let offset = v.as_ptr().align_offset(64).min(v.len());
+    let (prefix, aligned) = v.split_at(offset);
+
+    // unaligned prefix, at the end of a padded chunk
+    let mut padded = [0u8; 64];
+    padded[64 - prefix.len()..].copy_from_slice(prefix);
+    if !validator::is_ascii::<V>(&padded) {
+        return position(prefix);
+    }
+
+    // process aligned 64-byte chunks
+    let mut chunks = aligned.chunks_exact(64);
+    for (i, chunk) in (&mut chunks).enumerate() {
+        if !validator::is_ascii::<V>(chunk.try_into().unwrap()) {
+            return position(chunk).map(|index| offset + i * 64 + index);
+        }
+    }
+
+    // handle remainder
+    let remainder = chunks.remainder();
+    let mut padded = [0u8; 64];
+    padded[..remainder.len()].copy_from_slice(remainder);
+    if !validator::is_ascii::<V>(&padded) {
+        return position(remainder).map(|index| v.len() - remainder.len() + index);
+    }
+
+    None
+}
+
+/// Searches a slice byte by byte.
+#[inline(always)]
+fn position(v: &[u8]) -> Option<usize> {
+    v.iter().position(|byte| !byte.is_ascii())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::Simd8x16;
+
+    #[test]
+    fn first_non_ascii_matches_position() {
+        let bytes = [b'a'; 400];
+
+        // every alignment of the start, and non-ASCII bytes in the prefix, chunks and remainder
+        for start in 0..64 {
+            let v = &bytes[start..];
+            assert_eq!(first_non_ascii(v), None);
+            assert_eq!(first_non_ascii_simd::<Simd8x16>(v), None);
+
+            for index in (0..v.len()).step_by(5) {
+                let mut invalid = bytes;
+                invalid[start + index] = 0x80 + index as u8 % 0x80;
+
+                // a later byte must not hide the first one
+                invalid[invalid.len() - 1] = 0xFF;
+
+                let v = &invalid[start..];
+                assert_eq!(first_non_ascii(v), Some(index));
+                assert_eq!(first_non_ascii_simd::<Simd8x16>(v), Some(index));
+                assert_eq!(from_ascii(v), Err(AsciiError::new(index)));
+                assert!(!is_ascii(v));
+            }
+        }
+    }
+
+    #[test]
+    fn short_inputs() {
+        assert!(is_ascii(b""));
+        assert_eq!(from_ascii(b"abc"), Ok("abc"));
+        assert_eq!(first_non_ascii(b"abc\x7F\x80"), Some(4));
+    }
+}
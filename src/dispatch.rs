@@ -0,0 +1,369 @@
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::simd::{Simd8x16, Simd8x32, Simd8x64};
+use crate::validator::{COUNT_CHARS, COUNT_UTF16};
+use crate::transcode::{self, Unit};
+use crate::{Utf16Error, Utf8Error, Utf8Validator, ascii, utf8, utf16};
+
+/// A validation kernel for slices of at least 128 bytes.
+type Kernel = unsafe fn(&[u8]) -> Result<(), Utf8Error>;
+
+/// A kernel that also counts the chars or UTF-16 code units of slices of at least 128 bytes.
+type CountKernel = unsafe fn(&[u8]) -> Result<usize, Utf8Error>;
+
+/// A kernel that feeds consecutive 64-byte chunks to a streaming validator.
+type StreamKernel = unsafe fn(&mut Utf8Validator, &[u8]) -> Result<(), Utf8Error>;
+
+/// A kernel that decodes UTF-8 to code units of type `U`, validating it in the same pass.
+type DecodeKernel<U> = unsafe fn(&[u8], &mut [U]) -> Result<usize, Utf8Error>;
+
+/// A kernel that encodes UTF-16 to UTF-8, failing on or replacing unpaired surrogates.
+type EncodeKernel = unsafe fn(&[u16], &mut [u8]) -> Result<usize, Utf16Error>;
+
+/// A kernel that searches slices of at least 128 bytes for the first non-ASCII byte.
+type AsciiKernel = unsafe fn(&[u8]) -> Option<usize>;
+
+/// The kernels for one level of CPU features.
+struct Kernels {
+    supported: fn() -> bool,
+    validate: Kernel,
+    count_chars: CountKernel,
+    utf16_len: CountKernel,
+    first_non_ascii: AsciiKernel,
+    stream: StreamKernel,
+    decode_utf16: DecodeKernel<u16>,
+    decode_utf32: DecodeKernel<u32>,
+    encode_utf16: EncodeKernel,
+    encode_utf16_lossy: EncodeKernel,
+}
+
+impl Kernels {
+    /// The counting kernel selected by `COUNT`.
+    fn count<const COUNT: u8>(&self) -> CountKernel {
+        match COUNT {
+            COUNT_CHARS => self.count_chars,
+            _ => self.utf16_len,
+        }
+    }
+}
+
+/// The kernels selected for the CPU, null until the first use detects the CPU features.
+static KERNELS: AtomicPtr<Kernels> = AtomicPtr::new(ptr::null_mut());
+
+/// The kernels for each level of CPU features, from the best to the worst.
+static LEVELS: [&Kernels; 4] = [&AVX512, &AVX2, &SSSE3, &SCALAR];
+
+/// Kernels for CPUs with AVX-512 VBMI.
+static AVX512: Kernels = Kernels {
+    supported: || std::is_x86_feature_detected!("avx512bw") && std::is_x86_feature_detected!("avx512vbmi"),
+    validate: validate_avx512,
+    count_chars: count_avx512::<COUNT_CHARS>,
+    utf16_len: count_avx512::<COUNT_UTF16>,
+    first_non_ascii: ascii_avx512,
+    stream: stream_avx512,
+    decode_utf16: decode_avx512::<u16>,
+    decode_utf32: decode_avx512::<u32>,
+    encode_utf16: encode_avx2::<true>,
+    encode_utf16_lossy: encode_avx2::<false>,
+};
+
+/// Kernels for CPUs with AVX2.
+static AVX2: Kernels = Kernels {
+    supported: || std::is_x86_feature_detected!("avx2"),
+    validate: validate_avx2,
+    count_chars: count_avx2::<COUNT_CHARS>,
+    utf16_len: count_avx2::<COUNT_UTF16>,
+    first_non_ascii: ascii_avx2,
+    stream: stream_avx2,
+    decode_utf16: decode_avx2::<u16>,
+    decode_utf32: decode_avx2::<u32>,
+    encode_utf16: encode_avx2::<true>,
+    encode_utf16_lossy: encode_avx2::<false>,
+};
+
+/// Kernels for CPUs with SSSE3.
+static SSSE3: Kernels = Kernels {
+    supported: || std::is_x86_feature_detected!("ssse3"),
+    validate: validate_ssse3,
+    count_chars: count_ssse3::<COUNT_CHARS>,
+    utf16_len: count_ssse3::<COUNT_UTF16>,
+    first_non_ascii: ascii_sse2,
+    stream: stream_ssse3,
+    decode_utf16: decode_ssse3::<u16>,
+    decode_utf32: decode_ssse3::<u32>,
+    encode_utf16: encode_ssse3::<true>,
+    encode_utf16_lossy: encode_ssse3::<false>,
+};
+
+/// Kernels for CPUs without SSSE3, which lack the byte shuffles of the validator.
+static SCALAR: Kernels = Kernels {
+    supported: || true,
+    validate: validate_scalar,
+    count_chars: utf8::count_scalar::<COUNT_CHARS>,
+    utf16_len: utf8::count_scalar::<COUNT_UTF16>,
+    first_non_ascii: ascii_sse2,
+    stream: Utf8Validator::process_scalar,
+    decode_utf16: transcode::decode_scalar::<u16>,
+    decode_utf32: transcode::decode_scalar::<u32>,
+    encode_utf16: utf16::encode::<true, false>,
+    encode_utf16_lossy: utf16::encode::<false, false>,
+};
+
+/// Validates a slice with the best kernel for the CPU.
+#[inline]
+pub(crate) fn validate(v: &[u8]) -> Result<(), Utf8Error> {
+    unsafe { (kernels().validate)(v) }
+}
+
+/// Validates a slice and counts what `COUNT` selects with the best kernel for the CPU.
+#[inline]
+pub(crate) fn count<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    unsafe { kernels().count::<COUNT>()(v) }
+}
+
+/// Feeds consecutive chunks to a streaming validator with the best kernel for the CPU.
+#[inline]
+pub(crate) fn process(validator: &mut Utf8Validator, bytes: &[u8]) -> Result<(), Utf8Error> {
+    unsafe { (kernels().stream)(validator, bytes) }
+}
+
+/// Decodes UTF-8 to UTF-16 with the best kernel for the CPU.
+#[inline]
+pub(crate) fn decode_utf16(src: &[u8], dst: &mut [u16]) -> Result<usize, Utf8Error> {
+    unsafe { (kernels().decode_utf16)(src, dst) }
+}
+
+/// Decodes UTF-8 to UTF-32 with the best kernel for the CPU.
+#[inline]
+pub(crate) fn decode_utf32(src: &[u8], dst: &mut [u32]) -> Result<usize, Utf8Error> {
+    unsafe { (kernels().decode_utf32)(src, dst) }
+}
+
+/// Encodes UTF-16 to UTF-8 with the best kernel for the CPU, failing on unpaired surrogates.
+#[inline]
+pub(crate) fn encode_utf16(src: &[u16], dst: &mut [u8]) -> Result<usize, Utf16Error> {
+    unsafe { (kernels().encode_utf16)(src, dst) }
+}
+
+/// Encodes UTF-16 to UTF-8 with the best kernel for the CPU, replacing unpaired surrogates.
+#[inline]
+pub(crate) fn encode_utf16_lossy(src: &[u16], dst: &mut [u8]) -> Result<usize, Utf16Error> {
+    unsafe { (kernels().encode_utf16_lossy)(src, dst) }
+}
+
+/// Searches a slice for the first non-ASCII byte with the best kernel for the CPU.
+#[inline]
+pub(crate) fn first_non_ascii(v: &[u8]) -> Option<usize> {
+    unsafe { (kernels().first_non_ascii)(v) }
+}
+
+/// The kernels selected for the CPU.
+#[inline]
+fn kernels() -> &'static Kernels {
+    let kernels = KERNELS.load(Ordering::Relaxed);
+    if kernels.is_null() {
+        return detect();
+    }
+
+    // SAFETY: only pointers to the static tables are stored
+    unsafe { &*kernels }
+}
+
+/// Detects the CPU features on first use and caches the selected kernels.
+#[cold]
+fn detect() -> &'static Kernels {
+    let kernels = select();
+    KERNELS.store(ptr::from_ref(kernels).cast_mut(), Ordering::Relaxed);
+    kernels
+}
+
+/// Selects the best kernels supported by the CPU.
+fn select() -> &'static Kernels {
+    match LEVELS.into_iter().find(|kernels| (kernels.supported)()) {
+        Some(kernels) => kernels,
+        None => unreachable!("the scalar kernels run on every CPU"),
+    }
+}
+
+/// Kernel for CPUs with AVX-512 VBMI, using one 64-byte vector per chunk.
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+fn validate_avx512(v: &[u8]) -> Result<(), Utf8Error> {
+    utf8::validate_simd::<Simd8x64>(v)
+}
+
+/// Kernel for CPUs with AVX2, using 32-byte vectors.
+#[target_feature(enable = "avx2")]
+fn validate_avx2(v: &[u8]) -> Result<(), Utf8Error> {
+    utf8::validate_simd::<Simd8x32>(v)
+}
+
+/// Kernel for CPUs with SSSE3, using 16-byte vectors.
+#[target_feature(enable = "ssse3")]
+fn validate_ssse3(v: &[u8]) -> Result<(), Utf8Error> {
+    utf8::validate_simd::<Simd8x16>(v)
+}
+
+/// Kernel for CPUs without SSSE3, which lack the byte shuffles of the validator.
+fn validate_scalar(v: &[u8]) -> Result<(), Utf8Error> {
+    match core::str::from_utf8(v) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Utf8Error::from_core(err, v)),
+    }
+}
+
+/// Counting kernel for CPUs with AVX-512 VBMI.
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+fn count_avx512<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    utf8::count_simd::<Simd8x64, COUNT>(v)
+}
+
+/// Counting kernel for CPUs with AVX2.
+#[target_feature(enable = "avx2")]
+fn count_avx2<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    utf8::count_simd::<Simd8x32, COUNT>(v)
+}
+
+/// Counting kernel for CPUs with SSSE3.
+#[target_feature(enable = "ssse3")]
+fn count_ssse3<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    utf8::count_simd::<Simd8x16, COUNT>(v)
+}
+
+/// Streaming kernel for CPUs with AVX-512 VBMI.
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+fn stream_avx512(validator: &mut Utf8Validator, bytes: &[u8]) -> Result<(), Utf8Error> {
+    validator.process_simd::<Simd8x64>(bytes)
+}
+
+/// Streaming kernel for CPUs with AVX2.
+#[target_feature(enable = "avx2")]
+fn stream_avx2(validator: &mut Utf8Validator, bytes: &[u8]) -> Result<(), Utf8Error> {
+    validator.process_simd::<Simd8x32>(bytes)
+}
+
+/// Streaming kernel for CPUs with SSSE3.
+#[target_feature(enable = "ssse3")]
+fn stream_ssse3(validator: &mut Utf8Validator, bytes: &[u8]) -> Result<(), Utf8Error> {
+    validator.process_simd::<Simd8x16>(bytes)
+}
+
+/// Decoding kernel for CPUs with AVX-512 VBMI.
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+fn decode_avx512<U: Unit>(src: &[u8], dst: &mut [U]) -> Result<usize, Utf8Error> {
+    transcode::decode_simd::<Simd8x64, U>(src, dst)
+}
+
+/// Decoding kernel for CPUs with AVX2.
+#[target_feature(enable = "avx2")]
+fn decode_avx2<U: Unit>(src: &[u8], dst: &mut [U]) -> Result<usize, Utf8Error> {
+    transcode::decode_simd::<Simd8x32, U>(src, dst)
+}
+
+/// Decoding kernel for CPUs with SSSE3.
+#[target_feature(enable = "ssse3")]
+fn decode_ssse3<U: Unit>(src: &[u8], dst: &mut [U]) -> Result<usize, Utf8Error> {
+    transcode::decode_simd::<Simd8x16, U>(src, dst)
+}
+
+/// UTF-16 encoding kernel for CPUs with AVX2, also used with AVX-512, as blocks of 8 code units fill no wider vector.
+#[target_feature(enable = "avx2")]
+fn encode_avx2<const STRICT: bool>(src: &[u16], dst: &mut [u8]) -> Result<usize, Utf16Error> {
+    utf16::encode::<STRICT, true>(src, dst)
+}
+
+/// UTF-16 encoding kernel for CPUs with SSSE3.
+#[target_feature(enable = "ssse3")]
+fn encode_ssse3<const STRICT: bool>(src: &[u16], dst: &mut [u8]) -> Result<usize, Utf16Error> {
+    utf16::encode::<STRICT, true>(src, dst)
+}
+
+/// ASCII search kernel for CPUs with AVX-512 BW.
+#[target_feature(enable = "avx512bw")]
+fn ascii_avx512(v: &[u8]) -> Option<usize> {
+    ascii::first_non_ascii_simd::<Simd8x64>(v)
+}
+
+/// ASCII search kernel for CPUs with AVX2.
+#[target_feature(enable = "avx2")]
+fn ascii_avx2(v: &[u8]) -> Option<usize> {
+    ascii::first_non_ascii_simd::<Simd8x32>(v)
+}
+
+/// ASCII search kernel for all other CPUs, the search needs no more than SSE2.
+fn ascii_sse2(v: &[u8]) -> Option<usize> {
+    ascii::first_non_ascii_simd::<Simd8x16>(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn kernels_agree() {
+        let text = crate::fixtures::TEXT.repeat(16);
+        let bytes = text.as_bytes();
+
+        // all levels that can run on this CPU
+        for kernels in LEVELS.into_iter().filter(|kernels| (kernels.supported)()) {
+            assert_eq!(unsafe { (kernels.validate)(bytes) }, Ok(()));
+            assert_eq!(unsafe { (kernels.count_chars)(bytes) }, Ok(text.chars().count()));
+            assert_eq!(unsafe { (kernels.utf16_len)(bytes) }, Ok(text.encode_utf16().count()));
+            assert_eq!(unsafe { (kernels.first_non_ascii)(bytes) }, bytes.iter().position(|byte| !byte.is_ascii()));
+
+            // streaming, with a partial chunk at the end
+            let stream = |bytes: &[u8]| {
+                let mut validator = Utf8Validator::new();
+                unsafe { (kernels.stream)(&mut validator, bytes) }.and_then(|()| validator.finish())
+            };
+            assert_eq!(stream(bytes), Ok(()));
+
+            let mut utf16 = std::vec![0; bytes.len()];
+            let len = unsafe { (kernels.decode_utf16)(bytes, &mut utf16) }.unwrap();
+            assert!(utf16[..len].iter().copied().eq(text.encode_utf16()));
+
+            let mut utf32 = std::vec![0; bytes.len()];
+            let len = unsafe { (kernels.decode_utf32)(bytes, &mut utf32) }.unwrap();
+            assert!(utf32[..len].iter().copied().eq(text.chars().map(u32::from)));
+
+            // and back, with an unpaired surrogate in the middle
+            let mut units = text.encode_utf16().collect::<Vec<_>>();
+            let mut utf8 = std::vec![0; units.len() * 3];
+            assert_eq!(unsafe { (kernels.encode_utf16)(&units, &mut utf8) }, Ok(bytes.len()));
+            assert_eq!(&utf8[..bytes.len()], bytes);
+
+            let middle = units.len() / 2;
+            units[middle] = 0xDC00;
+            let expected = crate::utf16_to_utf8(&units, &mut utf8);
+            assert_eq!(unsafe { (kernels.encode_utf16)(&units, &mut utf8) }, expected);
+            let len = unsafe { (kernels.encode_utf16_lossy)(&units, &mut utf8) }.unwrap();
+            assert_eq!(&utf8[..len], std::string::String::from_utf16_lossy(&units).as_bytes());
+
+            // ends in the middle of the last char
+            let truncated = &bytes[..text.rfind('界').unwrap() + 1];
+            assert_eq!(stream(truncated), Err(crate::from_utf8(truncated).unwrap_err()));
+            assert_eq!(unsafe { (kernels.decode_utf16)(truncated, &mut utf16) }, Err(crate::from_utf8(truncated).unwrap_err()));
+            assert_eq!(unsafe { (kernels.decode_utf32)(truncated, &mut utf32) }, Err(crate::from_utf8(truncated).unwrap_err()));
+
+            for position in (0..bytes.len()).step_by(7) {
+                let mut invalid = bytes.to_vec();
+                invalid[position] = 0xFF;
+
+                let expected = Utf8Error::from_core(core::str::from_utf8(&invalid).unwrap_err(), &invalid);
+                assert_eq!(unsafe { (kernels.validate)(&invalid) }, Err(expected));
+                assert_eq!(unsafe { (kernels.count_chars)(&invalid) }, Err(expected));
+                assert_eq!(unsafe { (kernels.utf16_len)(&invalid) }, Err(expected));
+                assert_eq!(stream(&invalid), Err(expected));
+                assert_eq!(unsafe { (kernels.decode_utf16)(&invalid, &mut utf16) }, Err(expected));
+                assert_eq!(unsafe { (kernels.decode_utf32)(&invalid, &mut utf32) }, Err(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn selected_kernel() {
+        validate(b"warm up the kernel selection with a slice of 128 bytes or more, which is the minimum length for SIMD validation......").unwrap();
+        assert!(ptr::eq(KERNELS.load(Ordering::Relaxed), select()));
+    }
+}
@@ -1,9 +1,397 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
 /// A UTF-8 error.
-#[derive(Debug, Clone, PartialEq)]
-pub struct Utf8Error;
+///
+/// Mirrors [`core::str::Utf8Error`]: besides signalling that the input is not
+/// valid UTF-8, it describes where the first invalid sequence is located and
+/// why it is invalid.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf8Error {
+    valid_up_to: usize,
+    error_len: Option<u8>,
+    kind: Utf8ErrorKind,
+}
+
+/// A possible error value when converting a `Vec<u8>` into a `String`.
+///
+/// Mirrors [`alloc::string::FromUtf8Error`]: the original buffer can be
+/// recovered with [`into_bytes`](FromUtf8Error::into_bytes).
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FromUtf8Error {
+    bytes: Vec<u8>,
+    error: Utf8Error,
+}
+
+/// A possible error value when converting an `Rc<[u8]>` or `Arc<[u8]>` into a shared string slice.
+///
+/// Like [`FromUtf8Error`], but gives back the caller's reference to the bytes
+/// with [`into_inner`](FromSharedUtf8Error::into_inner).
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FromSharedUtf8Error<P> {
+    bytes: P,
+    error: Utf8Error,
+}
+
+/// A UTF-16 error.
+///
+/// Describes the first unpaired surrogate of the input with the same
+/// [`valid_up_to`](Utf16Error::valid_up_to) and
+/// [`error_len`](Utf16Error::error_len) as [`Utf8Error`], so that errors of
+/// both directions are handled alike. It is a separate type because its
+/// positions count code units instead of bytes, and because none of the
+/// [`Utf8ErrorKind`]s, which describe invalid byte sequences, applies to a
+/// surrogate. [`Utf32Error`] is separate for the same reasons.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf16Error {
+    valid_up_to: usize,
+    error_len: Option<u8>,
+}
+
+/// A UTF-32 error.
+///
+/// Points to the first code unit of the input that is not a Unicode scalar
+/// value, i.e. a surrogate or a value above U+10FFFF.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Utf32Error {
+    valid_up_to: usize,
+}
+
+/// An error when converting UTF-8 to Latin-1.
+///
+/// Points to the first char of the input above U+00FF, which has no Latin-1
+/// encoding, or to the first invalid UTF-8 sequence, which is described by
+/// [`utf8_error`](Latin1Error::utf8_error).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Latin1Error {
+    valid_up_to: usize,
+    utf8: Option<Utf8Error>,
+}
+
+/// An ASCII error.
+///
+/// Points to the first byte of the input above 0x7F.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AsciiError {
+    valid_up_to: usize,
+}
+
+/// The reason why a byte sequence is not valid UTF-8.
+///
+/// The SIMD validator only detects that a chunk is invalid. The kind is
+/// determined afterwards by a scalar rescan of the first invalid sequence,
+/// from its lead byte and the byte after it.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Utf8ErrorKind {
+    /// A lead byte is not followed by enough continuation bytes.
+    TooShort,
+    /// A continuation byte does not belong to any lead byte.
+    UnexpectedContinuation,
+    /// A code point is encoded with more bytes than necessary.
+    Overlong,
+    /// A UTF-16 surrogate (U+D800 to U+DFFF) is encoded.
+    Surrogate,
+    /// A code point above U+10FFFF is encoded, with a lead byte from 0xF4 to 0xF7.
+    TooLarge,
+    /// A byte from 0xF8 to 0xFF, which never occurs in UTF-8.
+    InvalidByte,
+    /// The input ends in the middle of a multibyte sequence.
+    Truncated,
+}
+
+impl Utf8Error {
+    #[inline]
+    pub(crate) const fn new(valid_up_to: usize, error_len: Option<u8>, kind: Utf8ErrorKind) -> Self {
+        Self { valid_up_to, error_len, kind }
+    }
+
+    /// Converts an error of the standard library, classifying it with the input `v`.
+    #[inline]
+    pub(crate) fn from_core(err: core::str::Utf8Error, v: &[u8]) -> Self {
+        let valid_up_to = err.valid_up_to();
+        let kind = Utf8ErrorKind::classify(&v[valid_up_to..], err.error_len());
+        Self::new(valid_up_to, err.error_len().map(|len| len as u8), kind)
+    }
+
+    /// Moves the error position by `base` bytes, e.g. from a chunk into the whole input.
+    #[inline]
+    pub(crate) const fn rebase(self, base: usize) -> Self {
+        Self::new(base + self.valid_up_to, self.error_len, self.kind)
+    }
+
+    /// Returns the index in the given input up to which valid UTF-8 was verified.
+    ///
+    /// It is the maximum index such that `from_utf8(&input[..index])` would
+    /// return `Ok(_)`.
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+
+    /// Provides more information about the failure.
+    ///
+    /// * `None`: the end of the input was reached unexpectedly. `valid_up_to()`
+    ///   is 1 to 3 bytes from the end of the input.
+    /// * `Some(len)`: an unexpected byte was encountered. The length provided is
+    ///   that of the invalid byte sequence that starts at the index given by
+    ///   `valid_up_to()`.
+    #[inline]
+    pub const fn error_len(&self) -> Option<usize> {
+        match self.error_len {
+            Some(len) => Some(len as usize),
+            None => None,
+        }
+    }
+
+    /// Returns the reason why the sequence at `valid_up_to()` is invalid.
+    #[inline]
+    pub const fn kind(&self) -> Utf8ErrorKind {
+        self.kind
+    }
+}
 
 impl core::fmt::Display for Utf8Error {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "invalid utf-8 sequence")
+        match self.error_len {
+            Some(len) => write!(f, "invalid utf-8 sequence of {len} bytes from index {}: {}", self.valid_up_to, self.kind),
+            None => write!(f, "incomplete utf-8 byte sequence from index {}", self.valid_up_to),
+        }
+    }
+}
+
+impl core::error::Error for Utf8Error {}
+
+impl Utf16Error {
+    #[inline]
+    pub(crate) const fn new(valid_up_to: usize, error_len: Option<u8>) -> Self {
+        Self { valid_up_to, error_len }
+    }
+
+    /// Returns the index in the given input up to which valid UTF-16 was verified.
+    ///
+    /// It is the maximum index such that converting `&input[..index]` would
+    /// succeed.
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+
+    /// Provides more information about the failure.
+    ///
+    /// * `None`: the input ends with a high surrogate, which may be completed
+    ///   by the low surrogate at the start of more input.
+    /// * `Some(1)`: the code unit at the index given by `valid_up_to()` is a
+    ///   surrogate that is not part of a pair.
+    #[inline]
+    pub const fn error_len(&self) -> Option<usize> {
+        match self.error_len {
+            Some(len) => Some(len as usize),
+            None => None,
+        }
+    }
+}
+
+impl core::fmt::Display for Utf16Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.error_len {
+            Some(_) => write!(f, "unpaired surrogate at index {}", self.valid_up_to),
+            None => write!(f, "unpaired high surrogate at end of input at index {}", self.valid_up_to),
+        }
+    }
+}
+
+impl core::error::Error for Utf16Error {}
+
+impl Utf32Error {
+    #[inline]
+    pub(crate) const fn new(valid_up_to: usize) -> Self {
+        Self { valid_up_to }
+    }
+
+    /// Returns the index in the given input up to which valid UTF-32 was verified.
+    ///
+    /// It is the index of the first code unit that is not a scalar value.
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl core::fmt::Display for Utf32Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "invalid scalar value at index {}", self.valid_up_to)
+    }
+}
+
+impl core::error::Error for Utf32Error {}
+
+impl Latin1Error {
+    #[inline]
+    pub(crate) const fn new(valid_up_to: usize, utf8: Option<Utf8Error>) -> Self {
+        Self { valid_up_to, utf8 }
+    }
+
+    /// Returns the byte index in the given input up to which the text was converted.
+    ///
+    /// It is the start of the first char above U+00FF or of the first invalid
+    /// sequence.
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+
+    /// Returns the UTF-8 error if the input is invalid at `valid_up_to()`.
+    ///
+    /// It is the same error as that of [`from_utf8`](crate::from_utf8). `None`
+    /// means that the char at `valid_up_to()` is valid, but above U+00FF.
+    #[inline]
+    pub const fn utf8_error(&self) -> Option<Utf8Error> {
+        self.utf8
+    }
+}
+
+impl core::fmt::Display for Latin1Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.utf8 {
+            Some(err) => err.fmt(f),
+            None => write!(f, "char above U+00FF at index {}", self.valid_up_to),
+        }
+    }
+}
+
+impl core::error::Error for Latin1Error {}
+
+impl AsciiError {
+    #[inline]
+    pub(crate) const fn new(valid_up_to: usize) -> Self {
+        Self { valid_up_to }
+    }
+
+    /// Returns the index in the given input up to which valid ASCII was verified.
+    ///
+    /// It is the index of the first byte above 0x7F.
+    #[inline]
+    pub const fn valid_up_to(&self) -> usize {
+        self.valid_up_to
+    }
+}
+
+impl core::fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "non-ASCII byte at index {}", self.valid_up_to)
+    }
+}
+
+impl core::error::Error for AsciiError {}
+
+#[cfg(feature = "alloc")]
+impl FromUtf8Error {
+    #[inline]
+    pub(crate) const fn new(bytes: Vec<u8>, error: Utf8Error) -> Self {
+        Self { bytes, error }
+    }
+
+    /// Returns a slice of the bytes that were attempted to convert to a `String`.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the bytes that were attempted to convert to a `String`.
+    #[inline]
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Returns a [`Utf8Error`] with details about the conversion failure.
+    #[inline]
+    pub fn utf8_error(&self) -> Utf8Error {
+        self.error
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for FromUtf8Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::error::Error for FromUtf8Error {}
+
+#[cfg(feature = "alloc")]
+impl<P: core::ops::Deref<Target = [u8]>> FromSharedUtf8Error<P> {
+    #[inline]
+    pub(crate) const fn new(bytes: P, error: Utf8Error) -> Self {
+        Self { bytes, error }
+    }
+
+    /// Returns a slice of the bytes that were attempted to convert to a string slice.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the reference to the bytes that were attempted to convert to a string slice.
+    #[inline]
+    pub fn into_inner(self) -> P {
+        self.bytes
+    }
+
+    /// Returns a [`Utf8Error`] with details about the conversion failure.
+    #[inline]
+    pub fn utf8_error(&self) -> Utf8Error {
+        self.error
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P> core::fmt::Display for FromSharedUtf8Error<P> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Display::fmt(&self.error, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<P: core::fmt::Debug> core::error::Error for FromSharedUtf8Error<P> {}
+
+impl Utf8ErrorKind {
+    /// Classifies the invalid sequence at the start of `bytes`.
+    fn classify(bytes: &[u8], error_len: Option<usize>) -> Self {
+        if error_len.is_none() {
+            return Self::Truncated;
+        }
+
+        match (bytes[0], bytes.get(1).copied()) {
+            (0x80..=0xbf, _) => Self::UnexpectedContinuation,
+            (0xc0 | 0xc1, _) => Self::Overlong,
+            (0xf5..=0xf7, _) => Self::TooLarge,
+            (0xf8..=0xff, _) => Self::InvalidByte,
+            // the second byte is a continuation byte, but out of range for the lead byte
+            (0xe0, Some(0x80..=0x9f)) | (0xf0, Some(0x80..=0x8f)) => Self::Overlong,
+            (0xed, Some(0xa0..=0xbf)) => Self::Surrogate,
+            (0xf4, Some(0x90..=0xbf)) => Self::TooLarge,
+            _ => Self::TooShort,
+        }
+    }
+}
+
+impl core::fmt::Display for Utf8ErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let description = match self {
+            Self::TooShort => "missing continuation byte",
+            Self::UnexpectedContinuation => "unexpected continuation byte",
+            Self::Overlong => "overlong encoding",
+            Self::Surrogate => "encoded surrogate",
+            Self::TooLarge => "code point above U+10FFFF",
+            Self::InvalidByte => "invalid byte",
+            Self::Truncated => "truncated sequence",
+        };
+
+        f.write_str(description)
     }
-}
\ No newline at end of file
+}
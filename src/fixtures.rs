@@ -0,0 +1,4 @@
+//! Inputs shared by the tests.
+
+/// Lines of ASCII and of chars with 2 to 4 bytes, the latter across vector boundaries when repeated.
+pub(crate) const TEXT: &str = "832,qqq\n😀234\n456,bbb\nαβγδε ñ\n321,qqq\n世界\n123,ask\n291,aew\n";
@@ -0,0 +1,598 @@
+use std::boxed::Box;
+use std::io::{self, BufRead, Read, Write};
+
+use crate::utf8::is_continuation;
+use crate::{Utf8Error, Utf8ErrorKind, Utf8Validator, from_utf8_unchecked};
+
+/// Default buffer size of [`Utf8BufReader`].
+const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// A reader that validates all bytes read through it.
+///
+/// Wraps any [`Read`] and checks the data with a [`Utf8Validator`] as it
+/// passes through. Invalid input fails the read with
+/// [`io::ErrorKind::InvalidData`], carrying a [`Utf8Error`] whose position
+/// is the offset of the invalid sequence in the whole stream. The valid bytes
+/// before it are still returned by one read, the error by the next one.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::Read;
+/// use utf8simd::Utf8Reader;
+///
+/// let mut reader = Utf8Reader::new(&b"Hello \xF0\x90\x80World"[..]);
+///
+/// let mut buf = Vec::new();
+/// let err = reader.read_to_end(&mut buf).unwrap_err();
+/// assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+/// ```
+#[derive(Debug)]
+pub struct Utf8Reader<R> {
+    inner: R,
+    validator: Utf8Validator,
+}
+
+impl<R> Utf8Reader<R> {
+    /// Creates a new validating reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            validator: Utf8Validator::new(),
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the underlying reader bypasses validation.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Utf8Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // an error found by the previous read, after its valid bytes
+        self.validator.update(&[]).map_err(invalid_data)?;
+        let start = self.validator.position();
+
+        let len = self.inner.read(buf)?;
+
+        // the end of the input must not cut a sequence short
+        if len == 0 && !buf.is_empty() {
+            self.validator.finish().map_err(invalid_data)?;
+            return Ok(0);
+        }
+
+        match self.validator.update(&buf[..len]).and_then(|()| self.validator.check_partial()) {
+            Ok(_) => Ok(len),
+            // the valid bytes of this read are returned first, the validator keeps the error
+            Err(err) if err.valid_up_to() > start => Ok(err.valid_up_to() - start),
+            Err(err) => Err(invalid_data(err)),
+        }
+    }
+}
+
+/// A buffered reader that yields validated text.
+///
+/// Like [`std::io::BufReader`], but the contents of the buffer are always
+/// valid UTF-8 and end at a char boundary: an incomplete sequence at the end
+/// of a read is held back until the rest of it arrives. Besides implementing
+/// [`BufRead`], the text can be read as string slices with
+/// [`next_str`](Self::next_str).
+///
+/// # Examples
+///
+/// ```rust
+/// use utf8simd::Utf8BufReader;
+///
+/// let mut reader = Utf8BufReader::new("Hello, 世界!".as_bytes());
+///
+/// let mut text = String::new();
+/// while let Some(chunk) = reader.next_str()? {
+///     text.push_str(chunk);
+/// }
+///
+/// assert_eq!(text, "Hello, 世界!");
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Utf8BufReader<R> {
+    inner: R,
+    validator: Utf8Validator,
+    buf: Box<[u8]>,
+    /// Start of the unconsumed text
+    pos: usize,
+    /// End of the validated text
+    filled: usize,
+    /// End of the data read, including an incomplete sequence after `filled`
+    end: usize,
+}
+
+impl<R> Utf8BufReader<R> {
+    /// Creates a new buffered reader with a default capacity of 8 KiB.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUF_SIZE, inner)
+    }
+
+    /// Creates a new buffered reader with the given buffer capacity.
+    ///
+    /// # Panics
+    /// Panics if the capacity is too small to hold a 4-byte sequence.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        assert!(capacity >= 4, "buffer capacity must hold at least 4 bytes");
+
+        Self {
+            inner,
+            validator: Utf8Validator::new(),
+            buf: std::vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            end: 0,
+        }
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the underlying reader bypasses validation.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Returns the validated text that has not been consumed yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.filled]
+    }
+
+    /// Unwraps this reader, returning the underlying reader.
+    ///
+    /// Buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Utf8BufReader<R> {
+    /// Reads the next chunk of text, returning `None` at the end of the input.
+    ///
+    /// The chunk contains all buffered text, and more data is read from the
+    /// underlying reader if the buffer is empty.
+    ///
+    /// # Errors
+    /// Besides I/O errors of the underlying reader, this fails with
+    /// [`io::ErrorKind::InvalidData`] on invalid UTF-8, and with
+    /// [`io::ErrorKind::InvalidInput`] if [`consume`](BufRead::consume) was
+    /// called with an amount that does not end at a char boundary.
+    pub fn next_str(&mut self) -> io::Result<Option<&str>> {
+        self.fill_buf()?;
+
+        let (pos, filled) = (self.pos, self.filled);
+        if pos == filled {
+            return Ok(None);
+        }
+
+        if is_continuation(self.buf[pos]) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "buffer does not start at a char boundary"));
+        }
+
+        self.pos = filled;
+        Ok(Some(unsafe { from_utf8_unchecked(&self.buf[pos..filled]) }))
+    }
+
+    /// Reads from the underlying reader until at least one complete char is buffered, or the input ends.
+    fn refill(&mut self) -> io::Result<()> {
+        // keep an incomplete sequence from the previous read
+        self.buf.copy_within(self.filled..self.end, 0);
+        self.end -= self.filled;
+        self.pos = 0;
+        self.filled = 0;
+
+        while self.filled == 0 {
+            let len = self.inner.read(&mut self.buf[self.end..])?;
+
+            if len == 0 {
+                self.validator.finish().map_err(invalid_data)?;
+                break;
+            }
+
+            self.validator.update(&self.buf[self.end..self.end + len]).map_err(invalid_data)?;
+            self.end += len;
+
+            let incomplete = self.validator.check_partial().map_err(invalid_data)?;
+            self.filled = self.end - incomplete;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Utf8BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: Read> BufRead for Utf8BufReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled {
+            self.refill()?;
+        }
+
+        Ok(self.buffer())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+/// A writer that rejects output that is not valid UTF-8.
+///
+/// Wraps any [`Write`] and checks everything written through it with a
+/// [`Utf8Validator`]. A write fails with [`io::ErrorKind::InvalidData`] as soon
+/// as the data is provably invalid, before any of it is passed on. A
+/// multibyte sequence may be split across several writes, but
+/// [`flush`](Write::flush) and [`finish`](Self::finish) report a truncated
+/// sequence at the end of the output. Once invalid data has been rejected,
+/// all later writes fail as well.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::io::{ErrorKind, Write};
+/// use utf8simd::Utf8Writer;
+///
+/// let mut writer = Utf8Writer::new(Vec::new());
+/// writer.write_all("Hello, 世界!".as_bytes())?;
+///
+/// let output = writer.finish()?;
+/// assert_eq!(output, "Hello, 世界!".as_bytes());
+///
+/// let mut writer = Utf8Writer::new(Vec::new());
+/// let err = writer.write_all(b"\xC0\xAF").unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::InvalidData);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Utf8Writer<W> {
+    inner: W,
+    validator: Utf8Validator,
+}
+
+impl<W> Utf8Writer<W> {
+    /// Creates a new validating writer.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            validator: Utf8Validator::new(),
+        }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// Writing directly to the underlying writer bypasses validation.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Unwraps this writer without checking for a truncated sequence at the end.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Utf8Writer<W> {
+    /// Checks that the output does not end with a truncated sequence, flushes
+    /// and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.validator.finish().map_err(invalid_data)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for Utf8Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // the state only advances by what the inner writer accepts, invalid data is rejected for good
+        let checkpoint = self.validator.stage(buf).map_err(invalid_data)?;
+
+        let result = self.inner.write(buf);
+        self.validator.commit(checkpoint, buf, *result.as_ref().unwrap_or(&0)).map_err(invalid_data)?;
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // a truncated sequence is an error right now, but may still be completed later
+        let incomplete = self.validator.check_partial().map_err(invalid_data)?;
+        if incomplete > 0 {
+            let err = Utf8Error::new(self.validator.position() - incomplete, None, Utf8ErrorKind::Truncated);
+            return Err(invalid_data(err));
+        }
+
+        self.inner.flush()
+    }
+}
+
+/// Converts a validation error into an I/O error.
+fn invalid_data(err: Utf8Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::string::String;
+    use std::vec::Vec;
+
+    /// A reader that returns at most `size` bytes per read.
+    struct Chunked<'a> {
+        data: &'a [u8],
+        size: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.data.len().min(self.size).min(buf.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    use crate::fixtures::TEXT;
+
+    fn utf8_error(err: io::Error) -> Utf8Error {
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        *err.into_inner().unwrap().downcast::<Utf8Error>().unwrap()
+    }
+
+    #[test]
+    fn reader_passes_valid_input() {
+        let text = TEXT.repeat(8);
+
+        for size in [1, 3, 64, 1000] {
+            let mut reader = Utf8Reader::new(Chunked { data: text.as_bytes(), size });
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).unwrap();
+            assert_eq!(buf, text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn reader_rejects_invalid_input() {
+        let mut bytes = TEXT.repeat(8).into_bytes();
+        bytes[300] = 0xFF;
+
+        for size in [1, 3, 64, 1000] {
+            let mut reader = Utf8Reader::new(Chunked { data: &bytes, size });
+            let err = utf8_error(reader.read_to_end(&mut Vec::new()).unwrap_err());
+            assert_eq!(err, crate::from_utf8(&bytes).unwrap_err());
+        }
+
+        // truncated sequence at the end of the input
+        let mut reader = Utf8Reader::new(&"Hello, 世界!".as_bytes()[..8]);
+        let err = utf8_error(reader.read_to_end(&mut Vec::new()).unwrap_err());
+        assert_eq!(err.valid_up_to(), 7);
+        assert_eq!(err.error_len(), None);
+    }
+
+    #[test]
+    fn reader_returns_valid_prefix_first() {
+        let bytes = b"Hello, \xFF world";
+        let mut reader = Utf8Reader::new(&bytes[..]);
+
+        let mut buf = [0; 64];
+        assert_eq!(reader.read(&mut buf).unwrap(), 7);
+        assert_eq!(&buf[..7], b"Hello, ");
+
+        let err = utf8_error(reader.read(&mut buf).unwrap_err());
+        assert_eq!(err, crate::from_utf8(bytes).unwrap_err());
+    }
+
+    #[test]
+    fn buf_reader_splits_at_char_boundaries() {
+        let text = TEXT.repeat(8);
+
+        for (size, capacity) in [(1, 4), (2, 5), (7, 16), (1000, 64), (1000, 8192)] {
+            let mut reader = Utf8BufReader::with_capacity(capacity, Chunked { data: text.as_bytes(), size });
+
+            let mut output = String::new();
+            while let Some(chunk) = reader.next_str().unwrap() {
+                output.push_str(chunk);
+            }
+            assert_eq!(output, text);
+        }
+    }
+
+    #[test]
+    fn buf_reader_lines() {
+        let text = TEXT.repeat(8);
+
+        let reader = Utf8BufReader::with_capacity(16, Chunked { data: text.as_bytes(), size: 5 });
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(lines, text.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn buf_reader_rejects_invalid_input() {
+        let mut bytes = TEXT.repeat(8).into_bytes();
+        bytes[300] = 0xFF;
+
+        let mut reader = Utf8BufReader::with_capacity(32, Chunked { data: &bytes, size: 7 });
+        let err = loop {
+            match reader.next_str() {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("invalid input was accepted"),
+                Err(err) => break utf8_error(err),
+            }
+        };
+        assert_eq!(err, crate::from_utf8(&bytes).unwrap_err());
+    }
+
+    #[test]
+    fn writer_passes_valid_output() {
+        let text = TEXT.repeat(8);
+
+        for size in [1, 3, 64, 1000] {
+            let mut writer = Utf8Writer::new(Vec::new());
+            for chunk in text.as_bytes().chunks(size) {
+                writer.write_all(chunk).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap(), text.as_bytes());
+        }
+    }
+
+    #[test]
+    fn writer_rejects_invalid_output() {
+        let mut bytes = TEXT.repeat(8).into_bytes();
+        bytes[300] = 0xFF;
+
+        for size in [1, 3, 64, 1000] {
+            let mut writer = Utf8Writer::new(Vec::new());
+            let err = bytes.chunks(size).try_for_each(|chunk| writer.write_all(chunk)).unwrap_err();
+            assert_eq!(utf8_error(err), crate::from_utf8(&bytes).unwrap_err());
+
+            // nothing invalid made it through
+            assert!(writer.get_ref().len() <= 300);
+        }
+    }
+
+    /// A writer that fails every other call and accepts at most 5 bytes otherwise.
+    struct Flaky {
+        output: Vec<u8>,
+        fail: bool,
+        kind: io::ErrorKind,
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.fail = !self.fail;
+            if self.fail {
+                return Err(self.kind.into());
+            }
+
+            let len = buf.len().min(5);
+            self.output.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_retries_failed_writes() {
+        let text = TEXT.repeat(8);
+
+        // write_all retries interrupted writes
+        let mut writer = Utf8Writer::new(Flaky { output: Vec::new(), fail: false, kind: io::ErrorKind::Interrupted });
+        writer.write_all(text.as_bytes()).unwrap();
+        assert_eq!(writer.finish().unwrap().output, text.as_bytes());
+
+        // the caller retries the rest after WouldBlock
+        let mut writer = Utf8Writer::new(Flaky { output: Vec::new(), fail: false, kind: io::ErrorKind::WouldBlock });
+        let mut rest = text.as_bytes();
+        while !rest.is_empty() {
+            match writer.write(rest) {
+                Ok(len) => rest = &rest[len..],
+                Err(err) => assert_eq!(err.kind(), io::ErrorKind::WouldBlock),
+            }
+        }
+        assert_eq!(writer.finish().unwrap().output, text.as_bytes());
+    }
+
+    /// A writer that accepts at most `limit` bytes per call.
+    struct Limited {
+        output: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let len = buf.len().min(self.limit);
+            self.output.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn writer_advances_by_accepted_bytes() {
+        let text = TEXT.repeat(8);
+        let mut invalid = text.clone().into_bytes();
+        invalid[300] = 0xFF;
+
+        // the accepted bytes end before, within and after the chunks completed by a write
+        for (size, limit) in [(150, 7), (150, 100), (1000, 130)] {
+            let mut writer = Utf8Writer::new(Limited { output: Vec::new(), limit });
+            writer.write_all(&text.as_bytes()[..10]).unwrap();
+            for chunk in text.as_bytes()[10..].chunks(size) {
+                writer.write_all(chunk).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap().output, text.as_bytes());
+
+            let mut writer = Utf8Writer::new(Limited { output: Vec::new(), limit });
+            writer.write_all(&invalid[..10]).unwrap();
+            let err = invalid[10..].chunks(size).try_for_each(|chunk| writer.write_all(chunk)).unwrap_err();
+            assert_eq!(utf8_error(err), crate::from_utf8(&invalid).unwrap_err());
+        }
+    }
+
+    #[test]
+    fn writer_reports_truncated_output() {
+        let bytes = "Hello, 世界!".as_bytes();
+
+        let mut writer = Utf8Writer::new(Vec::new());
+        writer.write_all(&bytes[..8]).unwrap();
+
+        let err = utf8_error(writer.flush().unwrap_err());
+        assert_eq!(err.valid_up_to(), 7);
+        assert_eq!(err.error_len(), None);
+
+        // the sequence can still be completed
+        writer.write_all(&bytes[8..]).unwrap();
+        writer.flush().unwrap();
+
+        let mut writer = Utf8Writer::new(Vec::new());
+        writer.write_all(&bytes[..8]).unwrap();
+        assert_eq!(utf8_error(writer.finish().unwrap_err()), err);
+    }
+
+    #[test]
+    fn buf_reader_rejects_split_char() {
+        let mut reader = Utf8BufReader::new("世界".as_bytes());
+        reader.fill_buf().unwrap();
+        reader.consume(1);
+
+        let err = reader.next_str().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
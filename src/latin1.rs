@@ -0,0 +1,282 @@
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+use crate::ascii::first_non_ascii;
+use crate::transcode::too_short;
+use crate::{Latin1Error, Utf8Error};
+
+/// Converts Latin-1 (ISO-8859-1) to UTF-8.
+///
+/// Every Latin-1 byte is the scalar value with the same number, so the
+/// conversion cannot fail. Writes the UTF-8 encoding of `src` to the start of
+/// `dst` and returns the number of bytes written. Runs of ASCII are found with
+/// the widest SIMD instructions of the CPU and copied unchanged, the bytes
+/// above 0x7F after them expand to two. Twice the length of `src` is always
+/// enough.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut utf8 = [0u8; 16];
+/// let len = utf8simd::latin1_to_utf8(b"Gr\xFC\xDFe", &mut utf8);
+/// assert_eq!(&utf8[..len], "Grüße".as_bytes());
+/// ```
+pub fn latin1_to_utf8(src: &[u8], dst: &mut [u8]) -> usize {
+    let mut read = 0;
+    let mut written = 0;
+
+    while read < src.len() {
+        let ascii = first_non_ascii(&src[read..]).unwrap_or(src.len() - read);
+        let Some(run) = dst.get_mut(written..written + ascii) else {
+            too_short();
+        };
+
+        run.copy_from_slice(&src[read..read + ascii]);
+        read += ascii;
+        written += ascii;
+
+        // the next chunk is converted byte by byte, instead of searching each short run of ASCII in it
+        let end = (read + 64).min(src.len());
+        for &byte in &src[read..end] {
+            if byte < 0x80 {
+                let Some(dst) = dst.get_mut(written) else {
+                    too_short();
+                };
+
+                *dst = byte;
+                written += 1;
+            } else {
+                let Some(dst) = dst.get_mut(written..written + 2) else {
+                    too_short();
+                };
+
+                dst[0] = 0xc0 | (byte >> 6);
+                dst[1] = 0x80 | (byte & 0x3f);
+                written += 2;
+            }
+        }
+
+        read = end;
+    }
+
+    written
+}
+
+/// Converts Latin-1 (ISO-8859-1) to a string.
+///
+/// See [`latin1_to_utf8`]. The string is allocated with twice the length of
+/// `src`, which is enough for any input.
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(utf8simd::latin1_to_string(b"caf\xE9"), "café");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn latin1_to_string(src: &[u8]) -> String {
+    let mut dst = alloc::vec![0; src.len() * 2];
+    let len = latin1_to_utf8(src, &mut dst);
+    dst.truncate(len);
+
+    // every Latin-1 byte is converted to a complete sequence
+    unsafe { String::from_utf8_unchecked(dst) }
+}
+
+/// Converts UTF-8 to Latin-1 (ISO-8859-1), validating it in the same pass.
+///
+/// Writes the Latin-1 encoding of `src` to the start of `dst` and returns the
+/// number of bytes written. Runs of ASCII are found with the widest SIMD
+/// instructions of the CPU and copied unchanged, the chars after them are
+/// narrowed one by one. The length of `src` is always enough.
+///
+/// # Errors
+/// Fails at the first char above U+00FF or the first invalid sequence, see
+/// [`Latin1Error`]. `dst` holds the conversion of the chars before it then.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text. Errors are always
+/// reported, even if `dst` is full before them.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut latin1 = [0u8; 16];
+/// let len = utf8simd::utf8_to_latin1("Grüße".as_bytes(), &mut latin1).unwrap();
+/// assert_eq!(&latin1[..len], b"Gr\xFC\xDFe");
+///
+/// let err = utf8simd::utf8_to_latin1("5 €".as_bytes(), &mut latin1).unwrap_err();
+/// assert_eq!((err.valid_up_to(), err.utf8_error()), (2, None));
+///
+/// let err = utf8simd::utf8_to_latin1(b"5 \xFF", &mut latin1).unwrap_err();
+/// assert_eq!(err.utf8_error(), utf8simd::from_utf8(b"5 \xFF").err());
+/// ```
+pub fn utf8_to_latin1(src: &[u8], dst: &mut [u8]) -> Result<usize, Latin1Error> {
+    let mut read = 0;
+    let mut written = 0;
+
+    while read < src.len() {
+        let ascii = first_non_ascii(&src[read..]).unwrap_or(src.len() - read);
+        let Some(run) = dst.get_mut(written..written + ascii) else {
+            return Err(first_error(src, read).unwrap_or_else(|| too_short()));
+        };
+
+        run.copy_from_slice(&src[read..read + ascii]);
+        read += ascii;
+        written += ascii;
+
+        // the next chunk is narrowed char by char, a char may continue after it
+        let end = (read + 64).min(src.len());
+        while read < end {
+            // check the char before the space in `dst`, so that an error is reported first
+            let (value, len) = narrow(src, read)?;
+
+            let Some(dst) = dst.get_mut(written) else {
+                // an error after the end of `dst` is still reported
+                return Err(first_error(src, read).unwrap_or_else(|| too_short()));
+            };
+
+            *dst = value;
+            read += len;
+            written += 1;
+        }
+    }
+
+    Ok(written)
+}
+
+/// Narrows the char at `index` to its Latin-1 byte, returned with the length of the char.
+#[inline(always)]
+fn narrow(src: &[u8], index: usize) -> Result<(u8, usize), Latin1Error> {
+    match src[index..] {
+        [byte @ 0x00..=0x7f, ..] => Ok((byte, 1)),
+        // U+0080 to U+00FF, the lead byte holds the 2 high bits
+        [lead @ (0xc2 | 0xc3), byte @ 0x80..=0xbf, ..] => Ok(((lead << 6) | (byte & 0x3f), 2)),
+        // a char above U+00FF or an invalid sequence, which ends within 4 bytes
+        _ => {
+            let sequence = &src[index..(index + 4).min(src.len())];
+            match core::str::from_utf8(sequence) {
+                Err(err) if err.valid_up_to() == 0 => Err(Latin1Error::new(index, Some(Utf8Error::from_core(err, sequence).rebase(index)))),
+                _ => Err(Latin1Error::new(index, None)),
+            }
+        }
+    }
+}
+
+/// Searches `src` for the first error from `index` on, once `dst` is full.
+#[cold]
+fn first_error(src: &[u8], mut index: usize) -> Option<Latin1Error> {
+    while index < src.len() {
+        match narrow(src, index) {
+            Ok((_, len)) => index += len,
+            Err(err) => return Some(err),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every byte, between ASCII runs that fill chunks.
+    fn latin1() -> [u8; 512] {
+        let mut bytes = [b'a'; 512];
+        for byte in 0..=255 {
+            bytes[100 + byte as usize + 72 * (byte as usize / 128)] = byte;
+        }
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = latin1();
+
+        for start in (0..100).step_by(7) {
+            for end in (start..bytes.len()).rev().step_by(11) {
+                let src = &bytes[start..end];
+
+                let mut expected = [0; 1024];
+                let mut len = 0;
+                for &byte in src {
+                    len += char::from(byte).encode_utf8(&mut expected[len..]).len();
+                }
+
+                let mut utf8 = [0; 1024];
+                assert_eq!(latin1_to_utf8(src, &mut utf8[..len]), len);
+                assert_eq!(utf8[..len], expected[..len]);
+
+                let mut latin1 = [0; 512];
+                assert_eq!(utf8_to_latin1(&utf8[..len], &mut latin1[..src.len()]), Ok(src.len()));
+                assert_eq!(&latin1[..src.len()], src);
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut utf8 = [0; 1024];
+        let len = latin1_to_utf8(&latin1(), &mut utf8);
+
+        for position in (0..len - 3).filter(|&i| (utf8[i] as i8) >= -0x40) {
+            for c in ['\u{100}', '€', '🦀'] {
+                let mut invalid = utf8;
+                invalid.copy_within(position..len, position + c.len_utf8());
+                c.encode_utf8(&mut invalid[position..]);
+
+                let mut latin1 = [0; 1024];
+                assert_eq!(utf8_to_latin1(&invalid[..len + c.len_utf8()], &mut latin1), Err(Latin1Error::new(position, None)));
+            }
+
+            // invalid sequences of every kind, which are reported like `from_utf8` reports them
+            for sequence in [&b"\xFF"[..], b"\x80", b"\xC3", b"\xC0\x80", b"\xE2\x82", b"\xED\xA0\x80", b"\xF4\x90\x80\x80"] {
+                let mut invalid = utf8;
+                invalid.copy_within(position..len, position + sequence.len());
+                invalid[position..position + sequence.len()].copy_from_slice(sequence);
+
+                let invalid = &invalid[..len + sequence.len()];
+                let err = crate::from_utf8(invalid).unwrap_err();
+                let mut latin1 = [0; 1024];
+                assert_eq!(utf8_to_latin1(invalid, &mut latin1), Err(Latin1Error::new(position, Some(err))));
+            }
+        }
+
+        // an incomplete sequence at the end
+        let mut truncated = utf8;
+        truncated[len..len + 2].copy_from_slice(b"\xE2\x82");
+        let err = crate::from_utf8(&truncated[..len + 2]).unwrap_err();
+        assert_eq!(utf8_to_latin1(&truncated[..len + 2], &mut [0; 1024]), Err(Latin1Error::new(len, Some(err))));
+    }
+
+    #[test]
+    fn error_before_short_buffer() {
+        // the errors are reported although `dst` is full before them
+        assert_eq!(utf8_to_latin1("é€".as_bytes(), &mut [0; 1]), Err(Latin1Error::new(2, None)));
+        assert_eq!(utf8_to_latin1("éé€".as_bytes(), &mut [0; 1]), Err(Latin1Error::new(4, None)));
+
+        let mut bytes = [b'a'; 200];
+        bytes[150..153].copy_from_slice("€".as_bytes());
+        assert_eq!(utf8_to_latin1(&bytes, &mut [0; 10]), Err(Latin1Error::new(150, None)));
+
+        bytes[150] = 0xFF;
+        let err = crate::from_utf8(&bytes).unwrap_err();
+        assert_eq!(utf8_to_latin1(&bytes, &mut [0; 10]), Err(Latin1Error::new(150, Some(err))));
+    }
+
+    #[test]
+    #[should_panic(expected = "too short")]
+    fn short_buffer() {
+        let mut utf8 = [0; 127];
+        let _ = latin1_to_utf8(&[0xFF; 64], &mut utf8);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn string() {
+        let bytes = latin1();
+        assert_eq!(latin1_to_string(&bytes), bytes.iter().map(|&byte| char::from(byte)).collect::<String>());
+    }
+}
@@ -5,17 +5,60 @@
 
 #![no_std]
 
-#![feature(portable_simd)]
-#![feature(core_intrinsics)]
-#![feature(generic_const_exprs)]
+// the portable SIMD backend is only used on architectures without a native one
+#![cfg_attr(any(utf8simd_portable, all(test, feature = "nightly")), feature(portable_simd))]
+#![cfg_attr(feature = "nightly", feature(core_intrinsics))]
+#![cfg_attr(feature = "nightly", allow(internal_features))]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod ascii;
+#[cfg(utf8simd_dispatch)]
+mod dispatch;
 mod error;
+#[cfg(test)]
+mod fixtures;
+#[cfg(feature = "std")]
+mod io;
+mod latin1;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod simd;
+#[cfg(feature = "alloc")]
+mod string;
+#[cfg(any(test, feature = "swar"))]
+mod swar;
+mod transcode;
+mod utf16;
+mod utf32;
 mod utf8;
 mod validator;
 
-pub use error::Utf8Error;
-pub use utf8::{from_utf8, from_utf8_unchecked};
+pub use ascii::{first_non_ascii, from_ascii, is_ascii};
+pub use error::{AsciiError, Latin1Error, Utf16Error, Utf32Error, Utf8Error, Utf8ErrorKind};
+#[cfg(feature = "alloc")]
+pub use error::{FromSharedUtf8Error, FromUtf8Error};
+#[cfg(feature = "std")]
+pub use io::{Utf8BufReader, Utf8Reader, Utf8Writer};
+pub use latin1::{latin1_to_utf8, utf8_to_latin1};
+#[cfg(feature = "alloc")]
+pub use latin1::latin1_to_string;
+#[cfg(feature = "rayon")]
+pub use parallel::from_utf8_parallel;
+pub use utf8::{count_chars, from_utf8, from_utf8_mut, from_utf8_unchecked, from_utf8_unchecked_mut, from_utf8_with_len, utf16_len};
+#[cfg(feature = "alloc")]
+pub use string::{from_boxed_utf8, from_boxed_utf8_unchecked, from_rc_utf8, from_utf8_lossy, from_utf8_vec};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+pub use string::from_arc_utf8;
+pub use utf16::{utf16_to_utf8, utf16_to_utf8_lossy, utf8_to_utf16};
+#[cfg(feature = "alloc")]
+pub use utf16::utf8_to_utf16_vec;
+pub use utf32::{utf8_to_chars, utf8_to_utf32, utf32_to_utf8};
+#[cfg(feature = "alloc")]
+pub use utf32::utf8_to_chars_vec;
 pub use validator::Utf8Validator;
 
 /// A UTF-8 validation result.
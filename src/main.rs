@@ -0,0 +1,340 @@
+//! Command-line tool that checks whether files or standard input are valid UTF-8.
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use memmap2::Mmap;
+use utf8simd::{Utf8Error, Utf8Validator};
+
+const USAGE: &str = "\
+Usage: utf8simd [OPTIONS] [FILE]...
+
+Checks that every FILE is valid UTF-8 and reports the first invalid sequence
+in each one by line, column and byte offset. With no FILE, or when FILE is -,
+standard input is read.
+
+Options:
+  -q, --quiet        print nothing, only set the exit status
+  -c, --count-chars  print the number of chars of valid inputs
+  -j, --json         print one JSON object per input
+  -h, --help         print this help
+
+Exit status is 0 if all inputs are valid, 1 if any is not and 2 if any could
+not be read. Inputs after an unreadable one are still checked.";
+
+/// Size of the buffer standard input and other streams are read with.
+const BUF_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Default)]
+struct Options {
+    quiet: bool,
+    count_chars: bool,
+    json: bool,
+}
+
+/// Line and column of the end of the text seen so far, along with the number of chars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    /// Line number, starting at 1
+    line: usize,
+    /// Number of chars in the current line before the position
+    column: usize,
+    chars: usize,
+}
+
+impl Position {
+    fn new() -> Self {
+        Self { line: 1, column: 0, chars: 0 }
+    }
+
+    /// Moves the position past valid UTF-8 `text`.
+    fn advance(&mut self, text: &[u8]) {
+        let chars = |bytes: &[u8]| bytes.iter().filter(|&&b| (b as i8) >= -0x40).count();
+
+        self.chars += chars(text);
+
+        match text.iter().rposition(|&b| b == b'\n') {
+            Some(i) => {
+                self.line += text.iter().filter(|&&b| b == b'\n').count();
+                self.column = chars(&text[i + 1..]);
+            }
+            None => self.column += chars(text),
+        }
+    }
+}
+
+/// Outcome of validating one input.
+enum Report {
+    Valid { chars: usize },
+    Invalid { error: Utf8Error, position: Position },
+    Failed { error: io::Error },
+}
+
+impl Report {
+    /// Exit status for the input, the highest one of all inputs is returned.
+    fn status(&self) -> u8 {
+        match self {
+            Report::Valid { .. } => 0,
+            Report::Invalid { .. } => 1,
+            Report::Failed { .. } => 2,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut options = Options::default();
+    let mut paths = Vec::new();
+
+    for arg in std::env::args_os().skip(1) {
+        match arg.to_str() {
+            Some("-q" | "--quiet") => options.quiet = true,
+            Some("-c" | "--count-chars") => options.count_chars = true,
+            Some("-j" | "--json") => options.json = true,
+            Some("-h" | "--help") => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            Some(option) if option.starts_with('-') && option != "-" => {
+                eprintln!("utf8simd: unknown option '{option}'\n\n{USAGE}");
+                return ExitCode::from(2);
+            }
+            _ => paths.push(arg),
+        }
+    }
+
+    if paths.is_empty() {
+        paths.push("-".into());
+    }
+
+    let mut status = 0;
+    let mut stdout = io::stdout().lock();
+
+    for path in &paths {
+        let path = Path::new(path);
+
+        let result = if path == Path::new("-") { check_reader(io::stdin().lock()) } else { check_file(path) };
+        let name = if path == Path::new("-") { "<stdin>".into() } else { path.to_string_lossy() };
+
+        // an unreadable input does not stop the others from being checked
+        let report = result.unwrap_or_else(|error| Report::Failed { error });
+        status = status.max(report.status());
+
+        if options.quiet {
+            continue;
+        }
+
+        let printed = match &report {
+            _ if options.json => writeln!(stdout, "{}", json(&name, &report, &options)),
+            Report::Valid { chars } if options.count_chars => writeln!(stdout, "{chars} {name}"),
+            Report::Valid { .. } => Ok(()),
+            Report::Invalid { error, position } => {
+                eprintln!("{name}:{}:{}: byte {}: {}", position.line, position.column + 1, error.valid_up_to(), error.kind());
+                Ok(())
+            }
+            Report::Failed { error } => {
+                eprintln!("utf8simd: {name}: {error}");
+                Ok(())
+            }
+        };
+
+        // e.g. a closed pipe
+        if printed.is_err() {
+            return ExitCode::from(2);
+        }
+    }
+
+    ExitCode::from(status)
+}
+
+/// Validates a file, memory-mapping it if it is a regular file.
+fn check_file(path: &Path) -> io::Result<Report> {
+    let file = File::open(path)?;
+
+    if !file.metadata()?.is_file() {
+        return check_reader(file);
+    }
+
+    // SAFETY: the mapping is only read, a file modified concurrently can at worst
+    // yield a wrong result, just like reading it would
+    let map = unsafe { Mmap::map(&file)? };
+
+    match utf8simd::count_chars(&map) {
+        Ok(chars) => Ok(Report::Valid { chars }),
+        Err(error) => {
+            let mut position = Position::new();
+            position.advance(&map[..error.valid_up_to()]);
+            Ok(Report::Invalid { error, position })
+        }
+    }
+}
+
+/// Validates a stream chunk by chunk, without holding it in memory.
+fn check_reader(mut reader: impl Read) -> io::Result<Report> {
+    let mut validator = Utf8Validator::new();
+    let mut position = Position::new();
+    let mut buf = vec![0; BUF_SIZE];
+
+    // offset of `buf` in the stream, and length of the incomplete sequence at its start
+    let mut offset = 0;
+    let mut incomplete = 0;
+
+    loop {
+        let len = match reader.read(&mut buf[incomplete..]) {
+            Ok(len) => len,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        let result = match len {
+            0 => validator.finish().map(|()| 0),
+            _ => validator.update(&buf[incomplete..incomplete + len]).and_then(|()| validator.check_partial()),
+        };
+
+        // every sequence before `buf` is complete, so errors are located in it
+        let end = incomplete + len;
+        match result {
+            Ok(_) if len == 0 => return Ok(Report::Valid { chars: position.chars }),
+            Ok(remaining) => {
+                position.advance(&buf[..end - remaining]);
+                buf.copy_within(end - remaining..end, 0);
+                offset += end - remaining;
+                incomplete = remaining;
+            }
+            Err(error) => {
+                position.advance(&buf[..error.valid_up_to() - offset]);
+                return Ok(Report::Invalid { error, position });
+            }
+        }
+    }
+}
+
+/// Formats a report as a JSON object.
+fn json(name: &str, report: &Report, options: &Options) -> String {
+    let mut out = String::from("{\"path\":\"");
+    escape(&mut out, name);
+
+    match report {
+        Report::Valid { chars } => {
+            out.push_str("\",\"valid\":true");
+            if options.count_chars {
+                write!(out, ",\"chars\":{chars}").unwrap();
+            }
+        }
+        Report::Invalid { error, position } => {
+            let error_len = error.error_len().map_or("null".into(), |len| len.to_string());
+            write!(
+                out,
+                "\",\"valid\":false,\"line\":{},\"column\":{},\"offset\":{},\"error_len\":{error_len},\"kind\":\"{}\"",
+                position.line,
+                position.column + 1,
+                error.valid_up_to(),
+                error.kind(),
+            )
+            .unwrap();
+        }
+        Report::Failed { error } => {
+            out.push_str("\",\"valid\":null,\"error\":\"");
+            escape(&mut out, &error.to_string());
+            out.push('"');
+        }
+    }
+
+    out.push('}');
+    out
+}
+
+/// Appends a string to JSON output, escaping it for a string literal.
+fn escape(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader that returns at most `n` bytes per read.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.0.len().min(self.1).min(buf.len());
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn position() {
+        let mut position = Position::new();
+        position.advance("Grüße\n世界".as_bytes());
+        assert_eq!(position, Position { line: 2, column: 2, chars: 8 });
+
+        position.advance("!\n\n🦀".as_bytes());
+        assert_eq!(position, Position { line: 4, column: 1, chars: 12 });
+    }
+
+    #[test]
+    fn reader() {
+        let text = "Grüße\n世界! 🦀\n".repeat(10_000);
+
+        for n in [1, 3, 100, BUF_SIZE] {
+            match check_reader(Trickle(text.as_bytes(), n)).unwrap() {
+                Report::Valid { chars } => assert_eq!(chars, text.chars().count()),
+                Report::Invalid { error, .. } => panic!("{error}"),
+                Report::Failed { error } => panic!("{error}"),
+            }
+
+            let mut invalid = text.clone().into_bytes();
+            let at = text.len() - 7;
+            invalid[at] = 0xFF;
+
+            match check_reader(Trickle(&invalid, n)).unwrap() {
+                Report::Valid { .. } | Report::Failed { .. } => panic!("invalid input accepted"),
+                Report::Invalid { error, position } => {
+                    assert_eq!(error.valid_up_to(), at);
+                    assert_eq!(position, Position { line: 20_000, column: 2, chars: text.chars().count() - 4 });
+                }
+            }
+
+            // ends in the middle of the last char
+            match check_reader(Trickle(&text.as_bytes()[..text.len() - 3], n)).unwrap() {
+                Report::Valid { .. } | Report::Failed { .. } => panic!("truncated input accepted"),
+                Report::Invalid { error, position } => {
+                    assert_eq!(error.error_len(), None);
+                    assert_eq!(position.column, 4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn json_output() {
+        let options = Options { count_chars: true, ..Options::default() };
+
+        let valid = json("a\"b\\c\n", &Report::Valid { chars: 5 }, &options);
+        assert_eq!(valid, r#"{"path":"a\"b\\c\n","valid":true,"chars":5}"#);
+
+        let error = utf8simd::from_utf8(b"ab\n\xC0\xAF").unwrap_err();
+        let position = Position { line: 2, column: 0, chars: 3 };
+        let invalid = json("x", &Report::Invalid { error, position }, &options);
+        assert_eq!(invalid, r#"{"path":"x","valid":false,"line":2,"column":1,"offset":3,"error_len":1,"kind":"overlong encoding"}"#);
+
+        let error = io::Error::new(ErrorKind::NotFound, "no \"such\" file");
+        let failed = json("y", &Report::Failed { error }, &options);
+        assert_eq!(failed, r#"{"path":"y","valid":null,"error":"no \"such\" file"}"#);
+    }
+}
@@ -0,0 +1,175 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use rayon::prelude::*;
+
+use crate::{Utf8Error, from_utf8, from_utf8_unchecked, utf8};
+
+/// Inputs are split into ranges of at least this many bytes.
+const MIN_RANGE: usize = 1 << 20;
+
+/// Converts a slice of bytes to a string slice, validating it on all threads of the rayon thread pool.
+///
+/// The input is split into one range per thread, each validated independently
+/// with [`from_utf8`]. The split points are moved back to the start of a
+/// sequence, at most 3 bytes, so that no valid sequence crosses a range
+/// boundary. A sequence cut off at the end of a range is completed from the
+/// next one before the validation goes on. The reported error is the first
+/// one in the input, the same as for [`from_utf8`]. Inputs smaller than 2 MiB
+/// are validated on the current thread.
+///
+/// # Examples
+///
+/// ```rust
+/// let text = "Grüße, 世界! 🦀\n".repeat(1 << 18);
+/// let str = utf8simd::from_utf8_parallel(text.as_bytes()).unwrap();
+/// assert_eq!(str, text);
+/// ```
+pub fn from_utf8_parallel(v: &[u8]) -> Result<&str, Utf8Error> {
+    check(v)?;
+
+    Ok(unsafe { from_utf8_unchecked(v) })
+}
+
+/// Validates `v` in parallel if it fills at least 2 ranges of [`MIN_RANGE`] bytes, on the current thread otherwise.
+fn check(v: &[u8]) -> Result<(), Utf8Error> {
+    let count = rayon::current_num_threads().min(v.len() / MIN_RANGE);
+    if count < 2 {
+        return from_utf8(v).map(|_| ());
+    }
+
+    validate(v, count)
+}
+
+/// Validates `v` in `count` ranges in parallel, reporting the first error.
+fn validate(v: &[u8], count: usize) -> Result<(), Utf8Error> {
+    let error = split(v, count).into_par_iter().find_map_first(|range| {
+        let start = range.start;
+        from_utf8(&v[range]).err().map(|err| err.rebase(start))
+    });
+
+    match error {
+        // the ranges before are valid, so the cut off sequence starts at a char boundary
+        Some(err) if err.error_len().is_none() => resume(v, err.valid_up_to()),
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Validates `v` from a sequence at `from` that was cut off by the end of a range.
+///
+/// `split` only cuts through a sequence next to invalid bytes, e.g. a 4-byte
+/// sequence followed by a stray continuation byte, so the sequence is
+/// completed from the next range and the rest is validated again.
+#[cold]
+fn resume(v: &[u8], from: usize) -> Result<(), Utf8Error> {
+    let width = match v[from] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    };
+
+    let end = (from + width).min(v.len());
+    from_utf8(&v[from..end]).map_err(|err| err.rebase(from))?;
+    check(&v[end..]).map_err(|err| err.rebase(end))
+}
+
+/// Splits `v` into `count` ranges of about the same size, starting at sequence boundaries.
+fn split(v: &[u8], count: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::with_capacity(count);
+    let mut start = 0;
+
+    for i in 1..count {
+        let mut end = v.len() / count * i;
+
+        // look back at most 3 bytes for the start of a sequence, a longer run of
+        // continuation bytes is invalid anyway and resolved by `resume`
+        for _ in 0..3 {
+            if !utf8::is_continuation(v[end]) {
+                break;
+            }
+            end -= 1;
+        }
+
+        ranges.push(start..end);
+        start = end;
+    }
+
+    ranges.push(start..v.len());
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    #[test]
+    fn split_at_boundaries() {
+        let text = "a€😀".repeat(1000);
+        let v = text.as_bytes();
+
+        for count in 2..20 {
+            let ranges = split(v, count);
+            assert_eq!(ranges.len(), count);
+            assert_eq!(ranges[0].start, 0);
+            assert_eq!(ranges[count - 1].end, v.len());
+
+            for range in ranges {
+                assert!(text.is_char_boundary(range.start));
+                assert!(text.is_char_boundary(range.end));
+            }
+        }
+    }
+
+    #[test]
+    fn earliest_error() {
+        let text: String = "Grüße, 世界! 🦀\n".repeat(200);
+        let v = text.as_bytes();
+
+        for count in [2, 3, 7] {
+            assert_eq!(validate(v, count), Ok(()));
+
+            // invalid sequences around the split points and in between
+            let mut positions = Vec::from([0, 1, v.len() - 1]);
+            for range in split(v, count) {
+                positions.extend((range.start.saturating_sub(4)..range.start + 4).filter(|&i| i < v.len()));
+                positions.push(range.start + (range.end - range.start) / 2);
+            }
+
+            for position in positions {
+                for bytes in [&b"\x80"[..], b"\xF0\x9F", b"\xE2\x82\xAC\xAC", b"\xFF"] {
+                    let mut invalid = v.to_vec();
+                    let end = (position + bytes.len()).min(v.len());
+                    invalid[position..end].copy_from_slice(&bytes[..end - position]);
+
+                    // a later error must not hide the first one
+                    let last = invalid.len() - 1;
+                    invalid[last] = 0xC3;
+
+                    let expected = Utf8Error::from_core(core::str::from_utf8(&invalid).unwrap_err(), &invalid);
+                    assert_eq!(validate(&invalid, count), Err(expected), "{position}");
+                }
+            }
+
+            // runs of continuation bytes that make `split` cut through a sequence
+            for i in 1..count {
+                let mid = v.len() / count * i;
+
+                for (offset, bytes) in [(4, &b"\xF0\x9F\x98\x80\x80"[..]), (3, b"\xE2\x82\xAC\x80\x80"), (2, b"\x80\x80\x80\x80\x80")] {
+                    let mut invalid = v.to_vec();
+                    invalid[mid - offset..mid - offset + bytes.len()].copy_from_slice(bytes);
+
+                    let expected = Utf8Error::from_core(core::str::from_utf8(&invalid).unwrap_err(), &invalid);
+                    assert_eq!(validate(&invalid, count), Err(expected), "{mid}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn large_input() {
+        let text: String = "Grüße, 世界! 🦀\n".repeat(MIN_RANGE / 5);
+        assert_eq!(from_utf8_parallel(text.as_bytes()), Ok(text.as_str()));
+    }
+}
@@ -1,22 +1,48 @@
 use super::Simd8x16;
 
-use core::simd::Simd;
 use core::arch::aarch64::*;
 
+pub(super) type Repr = uint8x16_t;
+
 impl Simd8x16 {
-    #[inline]
-    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 where [(); { 16 - N } as usize]: {
-        let c = uint8x16_t::from(self.value);
-        let p = uint8x16_t::from(previous.value);
+    #[inline(always)]
+    pub fn splat(value: u8) -> Simd8x16 {
+        Simd8x16::from(unsafe { vdupq_n_u8(value) })
+    }
+
+    #[inline(always)]
+    pub fn and(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(unsafe { vandq_u8(self.value, other.value) })
+    }
+
+    #[inline(always)]
+    pub fn or(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(unsafe { vorrq_u8(self.value, other.value) })
+    }
+
+    #[inline(always)]
+    pub fn xor(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(unsafe { veorq_u8(self.value, other.value) })
+    }
+
+    #[inline(always)]
+    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 {
+        let c = self.value;
+        let p = previous.value;
 
-        let r = unsafe { vextq_u8::<{ 16 - N }>(p, c) };
+        let r = match N {
+            1 => unsafe { vextq_u8::<15>(p, c) },
+            2 => unsafe { vextq_u8::<14>(p, c) },
+            3 => unsafe { vextq_u8::<13>(p, c) },
+            _ => unreachable!("multibyte sequences look back at most 3 bytes"),
+        };
 
         Simd8x16::from(r)
     }
 
-    #[inline]
-    pub fn shr<const N: i32>(&self) -> Simd8x16 where [(); { 16 - N } as usize]: {
-        let input = uint8x16_t::from(self.value);
+    #[inline(always)]
+    pub fn shr<const N: i32>(&self) -> Simd8x16 {
+        let input = self.value;
 
         let shifted = unsafe { vshrq_n_u8::<N>(input) };
 
@@ -26,37 +52,83 @@ impl Simd8x16 {
         Simd8x16::from(result)
     }
 
-    #[inline]
+    #[inline(always)]
     pub fn lookup_16(&self, table: Simd8x16) -> Simd8x16 {
-        let indices = uint8x16_t::from(self.value);
-        let tbl = uint8x16_t::from(table.value);
+        let indices = self.value;
+        let tbl = table.value;
 
         let result = unsafe { vqtbl1q_u8(tbl, indices) };
 
         Simd8x16::from(result)
     }
 
-    #[inline]
+    #[inline(always)]
     pub fn saturating_sub(&self, other: Simd8x16) -> Simd8x16 {
-        let a = uint8x16_t::from(self.value);
-        let b = uint8x16_t::from(other.value);
+        let a = self.value;
+        let b = other.value;
 
         let result = unsafe { vqsubq_u8(a, b) };
 
         Simd8x16::from(result)
     }
+
+    #[inline(always)]
+    pub fn interleave(&self, other: Simd8x16) -> (Simd8x16, Simd8x16) {
+        let a = self.value;
+        let b = other.value;
+
+        let low = unsafe { vzip1q_u8(a, b) };
+        let high = unsafe { vzip2q_u8(a, b) };
+
+        (Simd8x16::from(low), Simd8x16::from(high))
+    }
+
+    #[inline(always)]
+    pub fn bitmask(&self) -> u16 {
+        let input = unsafe { vreinterpretq_s8_u8(self.value) };
+
+        // there is no movemask, weigh the lanes with the high bit set by their bit and add up each half
+        let bits = Simd8x16::from_array([1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128]).value;
+        let weighted = unsafe { vandq_u8(vreinterpretq_u8_s8(vshrq_n_s8::<7>(input)), bits) };
+
+        let low = unsafe { vaddv_u8(vget_low_u8(weighted)) };
+        let high = unsafe { vaddv_u8(vget_high_u8(weighted)) };
+
+        u16::from_le_bytes([low, high])
+    }
+
+    #[inline(always)]
+    pub fn count_continuations(&self) -> u32 {
+        let input = unsafe { vreinterpretq_s8_u8(self.value) };
+
+        // continuation bytes are the signed bytes below -64, count the lanes set to 1
+        let mask = unsafe { vcltq_s8(input, vdupq_n_s8(-0x40)) };
+        let ones = unsafe { vshrq_n_u8::<7>(mask) };
+
+        unsafe { vaddvq_u8(ones) as u32 }
+    }
+
+    #[inline(always)]
+    pub fn count_four_byte_leads(&self) -> u32 {
+        let input = self.value;
+
+        let mask = unsafe { vcgeq_u8(input, vdupq_n_u8(0xf0)) };
+        let ones = unsafe { vshrq_n_u8::<7>(mask) };
+
+        unsafe { vaddvq_u8(ones) as u32 }
+    }
 }
 
 impl From<Simd8x16> for uint8x16_t {
+    #[inline(always)]
     fn from(value: Simd8x16) -> Self {
-        value.value.into()
+        value.value
     }
 }
 
 impl From<uint8x16_t> for Simd8x16 {
+    #[inline(always)]
     fn from(value: uint8x16_t) -> Self {
-        Self {
-            value: Simd::from(value),
-        }
+        Self { value }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,224 @@
+use super::{Simd8x16, Vector};
+
+use core::ops::{BitAnd, BitOr, BitOrAssign, BitXor};
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+/// 32-element u8 AVX2 vector for UTF-8 validation
+///
+/// Byte shifts and shuffles of AVX2 work within each 128-bit lane, so the
+/// lookup tables are repeated in both lanes and `prev` carries bytes across
+/// the lanes explicitly.
+#[derive(Copy, Clone, Debug)]
+pub struct Simd8x32 {
+    value: __m256i,
+}
+
+impl Default for Simd8x32 {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::from(unsafe { _mm256_setzero_si256() })
+    }
+}
+
+impl Vector for Simd8x32 {
+    const LANES: usize = 32;
+
+    #[inline(always)]
+    unsafe fn load(ptr: *const u8) -> Self {
+        Self::from(unsafe { _mm256_loadu_si256(ptr as *const __m256i) })
+    }
+
+    #[inline(always)]
+    fn from_last(value: Simd8x16) -> Self {
+        let v = __m128i::from(value);
+
+        let r = unsafe { _mm256_inserti128_si256::<1>(_mm256_setzero_si256(), v) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn last(&self) -> Simd8x16 {
+        let r = unsafe { _mm256_extracti128_si256::<1>(self.value) };
+
+        Simd8x16::from(r)
+    }
+
+    #[inline(always)]
+    fn fold(&self) -> Simd8x16 {
+        let low = unsafe { _mm256_castsi256_si128(self.value) };
+
+        Simd8x16::from(low) | self.last()
+    }
+
+    #[inline(always)]
+    fn prev<const N: i32>(&self, previous: Self) -> Self {
+        // the high lane of the previous vector followed by the low lane of this one
+        let p = unsafe { _mm256_permute2x128_si256::<0x21>(previous.value, self.value) };
+
+        let r = match N {
+            1 => unsafe { _mm256_alignr_epi8::<15>(self.value, p) },
+            2 => unsafe { _mm256_alignr_epi8::<14>(self.value, p) },
+            3 => unsafe { _mm256_alignr_epi8::<13>(self.value, p) },
+            _ => unreachable!("multibyte sequences look back at most 3 bytes"),
+        };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn shr<const N: i32>(&self) -> Self {
+        let r = unsafe { _mm256_srli_epi16::<N>(self.value) };
+
+        Self::from(r) & Self::from(0xff >> N)
+    }
+
+    #[inline(always)]
+    fn lookup_16(&self, table: Simd8x16) -> Self {
+        let t = unsafe { _mm256_broadcastsi128_si256(__m128i::from(table)) };
+
+        let r = unsafe { _mm256_shuffle_epi8(t, self.value) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn saturating_sub(&self, other: Self) -> Self {
+        let r = unsafe { _mm256_subs_epu8(self.value, other.value) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn count_continuations(&self) -> u32 {
+        // continuation bytes are the signed bytes below -64
+        let r = unsafe { _mm256_movemask_epi8(_mm256_cmpgt_epi8(_mm256_set1_epi8(-0x40), self.value)) };
+
+        r.count_ones()
+    }
+
+    #[inline(always)]
+    fn count_four_byte_leads(&self) -> u32 {
+        // the bytes of at least 0xF0 are those unchanged by the maximum
+        let max = unsafe { _mm256_max_epu8(self.value, _mm256_set1_epi8(0xf0u8 as i8)) };
+        let r = unsafe { _mm256_movemask_epi8(_mm256_cmpeq_epi8(max, self.value)) };
+
+        r.count_ones()
+    }
+}
+
+impl BitAnd for Simd8x32 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::from(unsafe { _mm256_and_si256(self.value, rhs.value) })
+    }
+}
+
+impl BitOr for Simd8x32 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::from(unsafe { _mm256_or_si256(self.value, rhs.value) })
+    }
+}
+
+impl BitOrAssign for Simd8x32 {
+    #[inline(always)]
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl BitXor for Simd8x32 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::from(unsafe { _mm256_xor_si256(self.value, rhs.value) })
+    }
+}
+
+impl From<u8> for Simd8x32 {
+    #[inline(always)]
+    fn from(value: u8) -> Self {
+        Self::from(unsafe { _mm256_set1_epi8(value as i8) })
+    }
+}
+
+impl From<__m256i> for Simd8x32 {
+    #[inline(always)]
+    fn from(value: __m256i) -> Self {
+        Self { value }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Runs a test only if the CPU supports AVX2.
+    fn avx2() -> bool {
+        std::is_x86_feature_detected!("avx2")
+    }
+
+    /// The bytes of a 32-byte vector.
+    fn bytes(value: Simd8x32) -> [u8; 32] {
+        unsafe { core::mem::transmute(value.value) }
+    }
+
+    /// Two 16-byte halves as one 32-byte array.
+    fn concat(low: Simd8x16, high: Simd8x16) -> [u8; 32] {
+        let mut r = [0u8; 32];
+        r[..16].copy_from_slice(&low.to_array());
+        r[16..].copy_from_slice(&high.to_array());
+        r
+    }
+
+    #[test]
+    fn prev_crosses_lanes() {
+        if !avx2() {
+            return;
+        }
+
+        let input: [u8; 96] = core::array::from_fn(|i| i as u8 + 1);
+        let previous = unsafe { Simd8x32::load(input[32..].as_ptr()) };
+        let current = unsafe { Simd8x32::load(input[64..].as_ptr()) };
+
+        // the same as 16-byte vectors
+        let halves = input.chunks(16).map(|chunk| Simd8x16::from_array(chunk.try_into().unwrap())).collect::<std::vec::Vec<_>>();
+
+        assert_eq!(bytes(current.prev::<1>(previous)), concat(halves[4].prev::<1>(halves[3]), halves[5].prev::<1>(halves[4])));
+        assert_eq!(bytes(current.prev::<2>(previous)), concat(halves[4].prev::<2>(halves[3]), halves[5].prev::<2>(halves[4])));
+        assert_eq!(bytes(current.prev::<3>(previous)), concat(halves[4].prev::<3>(halves[3]), halves[5].prev::<3>(halves[4])));
+    }
+
+    #[test]
+    fn lookup_and_shift() {
+        if !avx2() {
+            return;
+        }
+
+        let input: [u8; 32] = core::array::from_fn(|i| (i as u8).wrapping_mul(37));
+        let table = Simd8x16::new(0, 11, 22, 33, 44, 55, 66, 77, 88, 99, 110, 121, 132, 143, 154, 165);
+
+        let value = unsafe { Simd8x32::load(input.as_ptr()) };
+        let low = Simd8x16::from_array(input[..16].try_into().unwrap());
+        let high = Simd8x16::from_array(input[16..].try_into().unwrap());
+
+        assert_eq!(bytes(value.shr::<4>()), concat(low.shr::<4>(), high.shr::<4>()));
+        assert_eq!(bytes(value.shr::<4>().lookup_16(table)), concat(low.shr::<4>().lookup_16(table), high.shr::<4>().lookup_16(table)));
+        assert_eq!(bytes(value.saturating_sub(Simd8x32::from(100))), concat(low.saturating_sub(Simd8x16::from(100)), high.saturating_sub(Simd8x16::from(100))));
+        assert_eq!(bytes(Simd8x32::from_last(high)), concat(Simd8x16::default(), high));
+        assert_eq!(value.last().to_array(), high.to_array());
+        assert_eq!(value.fold().to_array(), (low | high).to_array());
+        assert_eq!(value.count_continuations(), low.count_continuations() + high.count_continuations());
+        assert_eq!(value.count_four_byte_leads(), low.count_four_byte_leads() + high.count_four_byte_leads());
+    }
+}
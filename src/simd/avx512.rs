@@ -0,0 +1,253 @@
+use super::{Simd8x16, Vector};
+
+use core::ops::{BitAnd, BitOr, BitOrAssign, BitXor};
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+/// 64-element u8 AVX-512 vector for UTF-8 validation
+///
+/// A whole chunk fits into one register. The byte permutes of AVX-512 VBMI
+/// work across the 128-bit lanes, so `prev` is a single two-source permute
+/// and the lookup tables are indexed with `vpermb`.
+#[derive(Copy, Clone, Debug)]
+pub struct Simd8x64 {
+    value: __m512i,
+}
+
+impl Default for Simd8x64 {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::from(unsafe { _mm512_setzero_si512() })
+    }
+}
+
+impl Vector for Simd8x64 {
+    const LANES: usize = 64;
+
+    #[inline(always)]
+    unsafe fn load(ptr: *const u8) -> Self {
+        Self::from(unsafe { _mm512_loadu_si512(ptr as *const __m512i) })
+    }
+
+    #[inline(always)]
+    fn from_last(value: Simd8x16) -> Self {
+        let v = __m128i::from(value);
+
+        let r = unsafe { _mm512_inserti32x4::<3>(_mm512_setzero_si512(), v) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn last(&self) -> Simd8x16 {
+        let r = unsafe { _mm512_extracti32x4_epi32::<3>(self.value) };
+
+        Simd8x16::from(r)
+    }
+
+    #[inline(always)]
+    fn fold(&self) -> Simd8x16 {
+        let low = unsafe { _mm512_castsi512_si256(self.value) };
+        let high = unsafe { _mm512_extracti64x4_epi64::<1>(self.value) };
+        let r = unsafe { _mm256_or_si256(low, high) };
+
+        let low = unsafe { _mm256_castsi256_si128(r) };
+        let high = unsafe { _mm256_extracti128_si256::<1>(r) };
+
+        Simd8x16::from(low) | Simd8x16::from(high)
+    }
+
+    #[inline(always)]
+    fn is_ascii(&self) -> bool {
+        // the sign bits as a mask register
+        unsafe { _mm512_movepi8_mask(self.value) == 0 }
+    }
+
+    #[inline(always)]
+    fn prev<const N: i32>(&self, previous: Self) -> Self {
+        let index: &[u8; 64] = &const { prev_indices(N) };
+        let index = unsafe { _mm512_loadu_si512(index.as_ptr() as *const __m512i) };
+
+        let r = unsafe { _mm512_permutex2var_epi8(previous.value, index, self.value) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn shr<const N: i32>(&self) -> Self {
+        // the shift count of the immediate form is unsigned, pass it in a register instead
+        let r = unsafe { _mm512_srl_epi16(self.value, _mm_cvtsi32_si128(N)) };
+
+        Self::from(r) & Self::from(0xff >> N)
+    }
+
+    #[inline(always)]
+    fn lookup_16(&self, table: Simd8x16) -> Self {
+        let t = unsafe { _mm512_broadcast_i32x4(__m128i::from(table)) };
+
+        // indices are below 16, so every entry of the 64-byte table is a copy of the 16-byte table
+        let r = unsafe { _mm512_permutexvar_epi8(self.value, t) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn saturating_sub(&self, other: Self) -> Self {
+        let r = unsafe { _mm512_subs_epu8(self.value, other.value) };
+
+        Self::from(r)
+    }
+
+    #[inline(always)]
+    fn count_continuations(&self) -> u32 {
+        // continuation bytes are the signed bytes below -64
+        let r = unsafe { _mm512_cmplt_epi8_mask(self.value, _mm512_set1_epi8(-0x40)) };
+
+        r.count_ones()
+    }
+
+    #[inline(always)]
+    fn count_four_byte_leads(&self) -> u32 {
+        let r = unsafe { _mm512_cmpge_epu8_mask(self.value, _mm512_set1_epi8(0xf0u8 as i8)) };
+
+        r.count_ones()
+    }
+}
+
+impl BitAnd for Simd8x64 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self::from(unsafe { _mm512_and_si512(self.value, rhs.value) })
+    }
+}
+
+impl BitOr for Simd8x64 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self::from(unsafe { _mm512_or_si512(self.value, rhs.value) })
+    }
+}
+
+impl BitOrAssign for Simd8x64 {
+    #[inline(always)]
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+impl BitXor for Simd8x64 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::from(unsafe { _mm512_xor_si512(self.value, rhs.value) })
+    }
+}
+
+impl From<u8> for Simd8x64 {
+    #[inline(always)]
+    fn from(value: u8) -> Self {
+        Self::from(unsafe { _mm512_set1_epi8(value as i8) })
+    }
+}
+
+impl From<__m512i> for Simd8x64 {
+    #[inline(always)]
+    fn from(value: __m512i) -> Self {
+        Self { value }
+    }
+}
+
+/// Permutation indices for [`prev`](Vector::prev), evaluated at compile time.
+///
+/// Byte `i` is taken from index `64 - n + i` of the concatenation of the
+/// previous vector and the current one.
+const fn prev_indices(n: i32) -> [u8; 64] {
+    let mut indices = [0; 64];
+
+    let mut i = 0;
+    while i < 64 {
+        indices[i] = (64 - n as usize + i) as u8;
+        i += 1;
+    }
+
+    indices
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    /// Runs a test only if the CPU supports AVX-512 VBMI.
+    fn avx512() -> bool {
+        std::is_x86_feature_detected!("avx512bw") && std::is_x86_feature_detected!("avx512vbmi")
+    }
+
+    /// The bytes of a 64-byte vector.
+    fn bytes(value: Simd8x64) -> [u8; 64] {
+        unsafe { core::mem::transmute(value.value) }
+    }
+
+    /// Four 16-byte parts as one 64-byte array.
+    fn concat(parts: &[Simd8x16]) -> [u8; 64] {
+        let mut r = [0u8; 64];
+        for (chunk, part) in r.chunks_mut(16).zip(parts) {
+            chunk.copy_from_slice(&part.to_array());
+        }
+        r
+    }
+
+    /// The 16-byte parts of `input`.
+    fn parts(input: &[u8]) -> std::vec::Vec<Simd8x16> {
+        input.chunks(16).map(|chunk| Simd8x16::from_array(chunk.try_into().unwrap())).collect()
+    }
+
+    #[test]
+    fn prev_crosses_lanes() {
+        if !avx512() {
+            return;
+        }
+
+        let input: [u8; 128] = core::array::from_fn(|i| i as u8 + 1);
+        let previous = unsafe { Simd8x64::load(input.as_ptr()) };
+        let current = unsafe { Simd8x64::load(input[64..].as_ptr()) };
+
+        // the same as 16-byte vectors
+        let p = parts(&input);
+
+        assert_eq!(bytes(current.prev::<1>(previous)), concat(&[4, 5, 6, 7].map(|i| p[i].prev::<1>(p[i - 1]))));
+        assert_eq!(bytes(current.prev::<2>(previous)), concat(&[4, 5, 6, 7].map(|i| p[i].prev::<2>(p[i - 1]))));
+        assert_eq!(bytes(current.prev::<3>(previous)), concat(&[4, 5, 6, 7].map(|i| p[i].prev::<3>(p[i - 1]))));
+    }
+
+    #[test]
+    fn lookup_and_shift() {
+        if !avx512() {
+            return;
+        }
+
+        let input: [u8; 64] = core::array::from_fn(|i| (i as u8).wrapping_mul(37));
+        let table = Simd8x16::new(0, 11, 22, 33, 44, 55, 66, 77, 88, 99, 110, 121, 132, 143, 154, 165);
+
+        let value = unsafe { Simd8x64::load(input.as_ptr()) };
+        let p = parts(&input);
+
+        assert_eq!(bytes(value.shr::<4>()), concat(&p.iter().map(|p| p.shr::<4>()).collect::<std::vec::Vec<_>>()));
+        assert_eq!(bytes(value.shr::<4>().lookup_16(table)), concat(&p.iter().map(|p| p.shr::<4>().lookup_16(table)).collect::<std::vec::Vec<_>>()));
+        assert_eq!(bytes(value.saturating_sub(Simd8x64::from(100))), concat(&p.iter().map(|p| p.saturating_sub(Simd8x16::from(100))).collect::<std::vec::Vec<_>>()));
+        assert_eq!(bytes(Simd8x64::from_last(p[3])), concat(&[Simd8x16::default(), Simd8x16::default(), Simd8x16::default(), p[3]]));
+        assert_eq!(value.last().to_array(), p[3].to_array());
+        assert_eq!(value.fold().to_array(), (p[0] | p[1] | p[2] | p[3]).to_array());
+        assert!(!value.is_ascii());
+        assert!(Simd8x64::from(0x7f).is_ascii());
+        assert_eq!(value.count_continuations(), p.iter().map(|p| p.count_continuations()).sum());
+        assert_eq!(value.count_four_byte_leads(), p.iter().map(|p| p.count_four_byte_leads()).sum());
+    }
+}
@@ -1,31 +1,157 @@
-use core::simd::Simd;
 use core::ops::{BitAnd, BitOr, BitOrAssign, BitXor};
 
+/// A SIMD vector that 64-byte chunks are split into for UTF-8 validation.
+///
+/// The validator state between chunks is kept as 16-byte vectors, which is
+/// enough to look back the 3 bytes needed to complete multibyte sequences.
+///
+/// Functions generic over the vector type are always inlined, so that the
+/// CPU-specific kernels with `#[target_feature]` get code generated for their
+/// target features, instead of calling a copy compiled for the baseline CPU.
+pub trait Vector: Copy + Default + BitAnd<Output = Self> + BitOr<Output = Self> + BitOrAssign + BitXor<Output = Self> + From<u8> {
+    /// Number of bytes per vector
+    const LANES: usize;
+
+    /// Load a vector from `LANES` bytes at `ptr`
+    ///
+    /// # Safety
+    /// The bytes must be valid for reads.
+    unsafe fn load(ptr: *const u8) -> Self;
+
+    /// Create a vector whose last 16 bytes are `value`
+    fn from_last(value: Simd8x16) -> Self;
+
+    /// Extract the last 16 bytes
+    fn last(&self) -> Simd8x16;
+
+    /// Combine all 16-byte parts with a bitwise OR
+    fn fold(&self) -> Simd8x16;
+
+    /// Check that no byte has the high bit set
+    #[inline(always)]
+    fn is_ascii(&self) -> bool {
+        u128::from_ne_bytes(self.fold().to_array()) & u128::from_ne_bytes([0x80; 16]) == 0
+    }
+
+    /// Shift in the last `N` bytes (1 to 3) of the previous vector
+    fn prev<const N: i32>(&self, previous: Self) -> Self;
+
+    /// Shift every byte right by `N` bits
+    fn shr<const N: i32>(&self) -> Self;
+
+    /// Look up every byte (0 to 15) in a 16-entry table
+    fn lookup_16(&self, table: Simd8x16) -> Self;
+
+    /// Saturating subtraction of every byte
+    fn saturating_sub(&self, other: Self) -> Self;
+
+    /// Count the continuation bytes (`10______`)
+    fn count_continuations(&self) -> u32;
+
+    /// Count the lead bytes of 4-byte sequences (`11110___`) and the invalid bytes above them
+    fn count_four_byte_leads(&self) -> u32;
+}
+
 /// 16-element u8 SIMD vector for UTF-8 validation
-#[derive(Copy, Clone, Debug, Default)]
+///
+/// Wraps the vector register type of the architecture, the operations are
+/// implemented by the backend modules below.
+#[derive(Copy, Clone, Debug)]
 pub struct Simd8x16 {
-    value: Simd<u8, 16>,
+    value: Repr,
 }
 
 impl Simd8x16 {
     /// Create a new SIMD vector from 16 individual bytes
     #[allow(clippy::too_many_arguments)]
     pub fn new(v0: u8, v1: u8, v2: u8, v3: u8, v4: u8, v5: u8, v6: u8, v7: u8, v8: u8, v9: u8, v10: u8, v11: u8, v12: u8, v13: u8, v14: u8, v15: u8) -> Self {
-        Self {
-            value: Simd::from_array([v0, v1, v2, v3, v4, v5, v6, v7, v8, v9, v10, v11, v12, v13, v14, v15]),
-        }
+        Self::from_array([v0, v1, v2, v3, v4, v5, v6, v7, v8, v9, v10, v11, v12, v13, v14, v15])
+    }
+
+    /// Create a new SIMD vector from an array
+    #[inline(always)]
+    pub fn from_array(bytes: [u8; 16]) -> Self {
+        // every backend representation is 16 plain bytes
+        Self { value: unsafe { core::mem::transmute::<[u8; 16], Repr>(bytes) } }
+    }
+
+    /// Copy the bytes into an array
+    #[inline(always)]
+    pub fn to_array(self) -> [u8; 16] {
+        unsafe { core::mem::transmute::<Repr, [u8; 16]>(self.value) }
+    }
+
+    /// Check that all bytes are zero
+    #[inline(always)]
+    pub fn is_zero(&self) -> bool {
+        u128::from_ne_bytes(self.to_array()) == 0
     }
 
     /// Greater than bits (used for comparison)
-    #[inline]
+    #[inline(always)]
     pub fn gt_bits(&self, other: Self) -> Self {
         self.saturating_sub(other)
     }
+}
 
-    /// Access the underlying SIMD value
-    #[inline]
-    pub fn value(&self) -> Simd<u8, 16> {
-        self.value
+impl Default for Simd8x16 {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::splat(0)
+    }
+}
+
+impl Vector for Simd8x16 {
+    const LANES: usize = 16;
+
+    #[inline(always)]
+    unsafe fn load(ptr: *const u8) -> Self {
+        Self::from_array(unsafe { (ptr as *const [u8; 16]).read_unaligned() })
+    }
+
+    #[inline(always)]
+    fn from_last(value: Simd8x16) -> Self {
+        value
+    }
+
+    #[inline(always)]
+    fn last(&self) -> Simd8x16 {
+        *self
+    }
+
+    #[inline(always)]
+    fn fold(&self) -> Simd8x16 {
+        *self
+    }
+
+    #[inline(always)]
+    fn prev<const N: i32>(&self, previous: Self) -> Self {
+        Simd8x16::prev::<N>(self, previous)
+    }
+
+    #[inline(always)]
+    fn shr<const N: i32>(&self) -> Self {
+        Simd8x16::shr::<N>(self)
+    }
+
+    #[inline(always)]
+    fn lookup_16(&self, table: Simd8x16) -> Self {
+        Simd8x16::lookup_16(self, table)
+    }
+
+    #[inline(always)]
+    fn saturating_sub(&self, other: Self) -> Self {
+        Simd8x16::saturating_sub(self, other)
+    }
+
+    #[inline(always)]
+    fn count_continuations(&self) -> u32 {
+        Simd8x16::count_continuations(self)
+    }
+
+    #[inline(always)]
+    fn count_four_byte_leads(&self) -> u32 {
+        Simd8x16::count_four_byte_leads(self)
     }
 }
 
@@ -33,50 +159,41 @@ impl Simd8x16 {
 impl BitAnd for Simd8x16 {
     type Output = Self;
 
+    #[inline(always)]
     fn bitand(self, rhs: Self) -> Self::Output {
-        Self { value: self.value & rhs.value }
+        Simd8x16::and(self, rhs)
     }
 }
 
 impl BitOr for Simd8x16 {
     type Output = Self;
 
+    #[inline(always)]
     fn bitor(self, rhs: Self) -> Self::Output {
-        Self { value: self.value | rhs.value }
+        Simd8x16::or(self, rhs)
     }
 }
 
 impl BitOrAssign for Simd8x16 {
+    #[inline(always)]
     fn bitor_assign(&mut self, rhs: Self) {
-        self.value |= rhs.value;
+        *self = Simd8x16::or(*self, rhs);
     }
 }
 
 impl BitXor for Simd8x16 {
     type Output = Self;
 
+    #[inline(always)]
     fn bitxor(self, rhs: Self) -> Self::Output {
-        Self { value: self.value ^ rhs.value }
+        Simd8x16::xor(self, rhs)
     }
 }
 
 impl From<u8> for Simd8x16 {
+    #[inline(always)]
     fn from(value: u8) -> Self {
-        Self {
-            value: Simd::splat(value),
-        }
-    }
-}
-
-impl From<Simd<u8, 16>> for Simd8x16 {
-    fn from(value: Simd<u8, 16>) -> Self {
-        Self { value }
-    }
-}
-
-impl From<Simd8x16> for Simd<u8, 16> {
-    fn from(value: Simd8x16) -> Self {
-        value.value
+        Self::splat(value)
     }
 }
 
@@ -84,9 +201,57 @@ impl From<Simd8x16> for Simd<u8, 16> {
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod x86;
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use x86::Repr;
+
+// 32-byte vectors, used when AVX2 is enabled at compile time or detected at runtime
+#[cfg(utf8simd_avx2)]
+mod avx2;
+
+#[cfg(utf8simd_avx2)]
+pub use avx2::Simd8x32;
+
+// 64-byte vectors, used when AVX-512 VBMI is enabled at compile time or detected at runtime
+#[cfg(utf8simd_avx512)]
+mod avx512;
+
+#[cfg(utf8simd_avx512)]
+pub use avx512::Simd8x64;
+
 #[cfg(target_arch = "aarch64")]
 mod aarch64;
 
-// fallback portable implementation for other architectures
-#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
-mod portable;
\ No newline at end of file
+#[cfg(target_arch = "aarch64")]
+use aarch64::Repr;
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod wasm32;
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use wasm32::Repr;
+
+// fallback implementations for other architectures, tested against the native backends:
+// portable SIMD on nightly, plain arrays on stable
+#[cfg(any(utf8simd_portable, all(test, feature = "nightly")))]
+mod portable;
+
+#[cfg(utf8simd_portable)]
+use portable::Repr;
+
+#[cfg(any(test, utf8simd_scalar))]
+mod scalar;
+
+#[cfg(utf8simd_scalar)]
+use scalar::Repr;
+
+/// The widest vector type enabled at compile time.
+#[cfg(all(not(utf8simd_dispatch), any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx512bw", target_feature = "avx512vbmi"))]
+pub type Native = Simd8x64;
+
+/// The widest vector type enabled at compile time.
+#[cfg(all(not(utf8simd_dispatch), any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx2", not(all(target_feature = "avx512bw", target_feature = "avx512vbmi"))))]
+pub type Native = Simd8x32;
+
+/// The widest vector type enabled at compile time.
+#[cfg(all(not(utf8simd_dispatch), not(all(any(target_arch = "x86", target_arch = "x86_64"), target_feature = "avx2"))))]
+pub type Native = Simd8x16;
@@ -1,49 +1,261 @@
 use super::Simd8x16;
 
-use core::simd::{Simd, cmp::SimdPartialOrd, num::SimdUint};
+use core::simd::{Select, Simd, Swizzle, cmp::SimdPartialOrd, num::SimdUint};
 
+// architectures with a native backend only compile the portable operations for tests
+#[cfg(utf8simd_portable)]
+pub(super) type Repr = Simd<u8, 16>;
+
+#[cfg(utf8simd_portable)]
 impl Simd8x16 {
-    #[inline]
-    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 where [(); { 16 - N } as usize]: {
-        let mut result = [0u8; 16];
-        let prev_array = previous.value.to_array();
-        let curr_array = self.value.to_array();
+    #[inline(always)]
+    pub fn splat(value: u8) -> Simd8x16 {
+        Simd8x16 { value: Simd::splat(value) }
+    }
+
+    #[inline(always)]
+    pub fn and(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: self.value & other.value }
+    }
+
+    #[inline(always)]
+    pub fn or(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: self.value | other.value }
+    }
+
+    #[inline(always)]
+    pub fn xor(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: self.value ^ other.value }
+    }
+
+    #[inline(always)]
+    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: prev::<N>(self.value, previous.value) }
+    }
+
+    #[inline(always)]
+    pub fn shr<const N: i32>(&self) -> Simd8x16 {
+        Simd8x16 { value: shr::<N>(self.value) }
+    }
+
+    #[inline(always)]
+    pub fn lookup_16(&self, table: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: lookup_16(self.value, table.value) }
+    }
+
+    #[inline(always)]
+    pub fn saturating_sub(&self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: self.value.saturating_sub(other.value) }
+    }
+
+    #[inline(always)]
+    pub fn interleave(&self, other: Simd8x16) -> (Simd8x16, Simd8x16) {
+        let (low, high) = self.value.interleave(other.value);
+        (Simd8x16 { value: low }, Simd8x16 { value: high })
+    }
 
-        for i in 0..16 {
-            let src_idx = (16 - N as usize + i) % 32;
-            result[i] = if src_idx < 16 {
-                prev_array[src_idx]
-            } else {
-                curr_array[src_idx - 16]
-            };
+    #[inline(always)]
+    pub fn bitmask(&self) -> u16 {
+        bitmask(self.value)
+    }
+
+    #[inline(always)]
+    pub fn count_continuations(&self) -> u32 {
+        count_continuations(self.value)
+    }
+
+    #[inline(always)]
+    pub fn count_four_byte_leads(&self) -> u32 {
+        count_four_byte_leads(self.value)
+    }
+}
+
+/// Selects the last `N` bytes of the previous vector, followed by the first `16 - N` bytes of the current one.
+struct Prev<const N: i32>;
+
+impl<const N: i32> Swizzle<16> for Prev<N> {
+    const INDEX: [usize; 16] = {
+        let mut index = [0; 16];
+        let mut i = 0;
+        while i < 16 {
+            index[i] = 16 - N as usize + i;
+            i += 1;
+        }
+        index
+    };
+}
+
+#[inline(always)]
+fn prev<const N: i32>(value: Simd<u8, 16>, previous: Simd<u8, 16>) -> Simd<u8, 16> {
+    Prev::<N>::concat_swizzle(previous, value)
+}
+
+#[inline(always)]
+fn shr<const N: i32>(value: Simd<u8, 16>) -> Simd<u8, 16> {
+    value >> Simd::splat(N as u8)
+}
+
+#[inline(always)]
+fn lookup_16(value: Simd<u8, 16>, table: Simd<u8, 16>) -> Simd<u8, 16> {
+    let masked_indices = value & Simd::splat(0x0f);
+    let result = table.swizzle_dyn(masked_indices);
+
+    let mask = value.simd_lt(Simd::splat(16));
+    mask.select(result, Simd::splat(0))
+}
+
+/// The high bits of the bytes, the first byte in the lowest bit.
+#[inline(always)]
+fn bitmask(value: Simd<u8, 16>) -> u16 {
+    value.cast::<i8>().simd_lt(Simd::splat(0)).to_bitmask() as u16
+}
+
+/// Counts the continuation bytes, which are the signed bytes below -64.
+#[inline(always)]
+fn count_continuations(value: Simd<u8, 16>) -> u32 {
+    value.cast::<i8>().simd_lt(Simd::splat(-0x40)).to_bitmask().count_ones()
+}
+
+/// Counts the bytes of at least 0xF0.
+#[inline(always)]
+fn count_four_byte_leads(value: Simd<u8, 16>) -> u32 {
+    value.simd_ge(Simd::splat(0xf0)).to_bitmask().count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simd::Vector;
+    use crate::{utf8, Utf8Error};
+
+    /// The portable backend as a vector type, to run it next to the native backend.
+    #[derive(Copy, Clone, Debug, Default)]
+    struct Portable(Simd<u8, 16>);
+
+    impl Vector for Portable {
+        const LANES: usize = 16;
+
+        unsafe fn load(ptr: *const u8) -> Self {
+            Self(Simd::from_slice(unsafe { core::slice::from_raw_parts(ptr, 16) }))
         }
 
-        Simd8x16::from(Simd::from_array(result))
+        fn from_last(value: Simd8x16) -> Self {
+            Self(Simd::from_array(value.to_array()))
+        }
+
+        fn last(&self) -> Simd8x16 {
+            Simd8x16::from_array(self.0.to_array())
+        }
+
+        fn fold(&self) -> Simd8x16 {
+            self.last()
+        }
+
+        fn prev<const N: i32>(&self, previous: Self) -> Self {
+            Self(prev::<N>(self.0, previous.0))
+        }
+
+        fn shr<const N: i32>(&self) -> Self {
+            Self(shr::<N>(self.0))
+        }
+
+        fn lookup_16(&self, table: Simd8x16) -> Self {
+            Self(lookup_16(self.0, Simd::from_array(table.to_array())))
+        }
+
+        fn saturating_sub(&self, other: Self) -> Self {
+            Self(self.0.saturating_sub(other.0))
+        }
+
+        fn count_continuations(&self) -> u32 {
+            count_continuations(self.0)
+        }
+
+        fn count_four_byte_leads(&self) -> u32 {
+            count_four_byte_leads(self.0)
+        }
     }
 
-    #[inline]
-    pub fn shr<const N: i32>(&self) -> Simd8x16 where [(); { 16 - N } as usize]: {
-        let mut result = [0u8; 16];
-        let input = self.value.to_array();
-        for i in 0..16 {
-            result[i] = input[i] >> N;
+    impl core::ops::BitAnd for Portable {
+        type Output = Self;
+
+        fn bitand(self, rhs: Self) -> Self {
+            Self(self.0 & rhs.0)
         }
-        Simd8x16::from(Simd::from_array(result))
     }
 
-    #[inline]
-    pub fn lookup_16(&self, table: Simd8x16) -> Simd8x16 {
-        let masked_indices = self.value & Simd::splat(0x0f);
-        let result = table.value.swizzle_dyn(masked_indices);
+    impl core::ops::BitOr for Portable {
+        type Output = Self;
 
-        let mask = self.value.simd_lt(Simd::splat(16));
-        let final_result = mask.select(result, Simd::splat(0));
+        fn bitor(self, rhs: Self) -> Self {
+            Self(self.0 | rhs.0)
+        }
+    }
 
-        Simd8x16::from(final_result)
+    impl core::ops::BitOrAssign for Portable {
+        fn bitor_assign(&mut self, rhs: Self) {
+            self.0 |= rhs.0;
+        }
     }
 
-    #[inline]
-    pub fn saturating_sub(&self, other: Simd8x16) -> Simd8x16 {
-        Simd8x16::from(self.value.saturating_sub(other.value))
+    impl core::ops::BitXor for Portable {
+        type Output = Self;
+
+        fn bitxor(self, rhs: Self) -> Self {
+            Self(self.0 ^ rhs.0)
+        }
+    }
+
+    impl From<u8> for Portable {
+        fn from(value: u8) -> Self {
+            Self(Simd::splat(value))
+        }
+    }
+
+    #[test]
+    fn operations_match_native() {
+        let previous = core::array::from_fn(|i| i as u8 * 17);
+        let value = core::array::from_fn(|i| 0xff - i as u8 * 13);
+        let table = [0, 11, 22, 33, 44, 55, 66, 77, 88, 99, 110, 121, 132, 143, 154, 165];
+
+        let (value, native) = (Simd::from_array(value), Simd8x16::from_array(value));
+        let (previous, native_previous) = (Simd::from_array(previous), Simd8x16::from_array(previous));
+        let (table, native_table) = (Simd::from_array(table), Simd8x16::from_array(table));
+
+        assert_eq!(prev::<1>(value, previous).to_array(), native.prev::<1>(native_previous).to_array());
+        assert_eq!(prev::<2>(value, previous).to_array(), native.prev::<2>(native_previous).to_array());
+        assert_eq!(prev::<3>(value, previous).to_array(), native.prev::<3>(native_previous).to_array());
+        assert_eq!(shr::<4>(value).to_array(), native.shr::<4>().to_array());
+        assert_eq!(lookup_16(shr::<4>(value), table).to_array(), native.shr::<4>().lookup_16(native_table).to_array());
+        assert_eq!(lookup_16(value & Simd::splat(0x0f), table).to_array(), (native & Simd8x16::from(0x0f)).lookup_16(native_table).to_array());
+        assert_eq!(count_continuations(value), native.count_continuations());
+        assert_eq!(count_four_byte_leads(value), native.count_four_byte_leads());
+
+        let (low, high) = value.interleave(previous);
+        let (native_low, native_high) = native.interleave(native_previous);
+        assert_eq!((low.to_array(), high.to_array()), (native_low.to_array(), native_high.to_array()));
+        assert_eq!(bitmask(value), native.bitmask());
+    }
+
+    #[test]
+    fn validation_matches_native() {
+        const TEXT: &[u8] = crate::fixtures::TEXT.as_bytes();
+
+        let mut bytes = [0u8; 8 * TEXT.len()];
+        for chunk in bytes.chunks_mut(TEXT.len()) {
+            chunk.copy_from_slice(TEXT);
+        }
+        assert_eq!(utf8::validate_simd::<Portable>(&bytes), Ok(()));
+
+        for position in 0..bytes.len() {
+            for byte in [0x80, 0xC0, 0xE0, 0xED, 0xF4, 0xFF] {
+                let mut invalid = bytes;
+                invalid[position] = byte;
+
+                let expected = core::str::from_utf8(&invalid).map(|_| ()).map_err(|err| Utf8Error::from_core(err, &invalid));
+                assert_eq!(utf8::validate_simd::<Portable>(&invalid), expected);
+                assert_eq!(utf8::validate_simd::<Simd8x16>(&invalid), expected);
+            }
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,144 @@
+use super::Simd8x16;
+use crate::utf8::is_continuation;
+
+// architectures with a native backend only compile the scalar operations for tests
+#[cfg(utf8simd_scalar)]
+pub(super) type Repr = [u8; 16];
+
+#[cfg(utf8simd_scalar)]
+impl Simd8x16 {
+    #[inline(always)]
+    pub fn splat(value: u8) -> Simd8x16 {
+        Simd8x16 { value: [value; 16] }
+    }
+
+    #[inline(always)]
+    pub fn and(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: zip(self.value, other.value, |a, b| a & b) }
+    }
+
+    #[inline(always)]
+    pub fn or(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: zip(self.value, other.value, |a, b| a | b) }
+    }
+
+    #[inline(always)]
+    pub fn xor(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: zip(self.value, other.value, |a, b| a ^ b) }
+    }
+
+    #[inline(always)]
+    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: prev::<N>(self.value, previous.value) }
+    }
+
+    #[inline(always)]
+    pub fn shr<const N: i32>(&self) -> Simd8x16 {
+        Simd8x16 { value: self.value.map(|byte| byte >> N) }
+    }
+
+    #[inline(always)]
+    pub fn lookup_16(&self, table: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: lookup_16(self.value, table.value) }
+    }
+
+    #[inline(always)]
+    pub fn saturating_sub(&self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16 { value: zip(self.value, other.value, u8::saturating_sub) }
+    }
+
+    #[inline(always)]
+    pub fn interleave(&self, other: Simd8x16) -> (Simd8x16, Simd8x16) {
+        let (low, high) = interleave(self.value, other.value);
+        (Simd8x16 { value: low }, Simd8x16 { value: high })
+    }
+
+    #[inline(always)]
+    pub fn bitmask(&self) -> u16 {
+        bitmask(self.value)
+    }
+
+    #[inline(always)]
+    pub fn count_continuations(&self) -> u32 {
+        count_continuations(self.value)
+    }
+
+    #[inline(always)]
+    pub fn count_four_byte_leads(&self) -> u32 {
+        count_four_byte_leads(self.value)
+    }
+}
+
+/// Combines the bytes of two arrays.
+#[inline(always)]
+fn zip(a: [u8; 16], b: [u8; 16], f: impl Fn(u8, u8) -> u8) -> [u8; 16] {
+    core::array::from_fn(|i| f(a[i], b[i]))
+}
+
+/// The last `N` bytes of `previous`, followed by the first `16 - N` bytes of `value`.
+#[inline(always)]
+fn prev<const N: i32>(value: [u8; 16], previous: [u8; 16]) -> [u8; 16] {
+    let n = N as usize;
+    core::array::from_fn(|i| if i < n { previous[16 - n + i] } else { value[i - n] })
+}
+
+/// Looks up every byte in `table`, indices of 16 and above select 0.
+#[inline(always)]
+fn lookup_16(value: [u8; 16], table: [u8; 16]) -> [u8; 16] {
+    value.map(|index| table.get(index as usize).copied().unwrap_or(0))
+}
+
+/// The bytes of both arrays in turn, the first 8 pairs and the last 8 pairs.
+#[inline(always)]
+fn interleave(a: [u8; 16], b: [u8; 16]) -> ([u8; 16], [u8; 16]) {
+    let pairs = |start: usize| core::array::from_fn(|i| if i % 2 == 0 { a[start + i / 2] } else { b[start + i / 2] });
+    (pairs(0), pairs(8))
+}
+
+/// The high bits of the bytes, the first byte in the lowest bit.
+#[inline(always)]
+fn bitmask(value: [u8; 16]) -> u16 {
+    value.iter().rev().fold(0, |bits, &byte| bits << 1 | (byte >> 7) as u16)
+}
+
+/// Counts the continuation bytes.
+#[inline(always)]
+fn count_continuations(value: [u8; 16]) -> u32 {
+    value.iter().filter(|&&byte| is_continuation(byte)).count() as u32
+}
+
+/// Counts the bytes of at least 0xF0.
+#[inline(always)]
+fn count_four_byte_leads(value: [u8; 16]) -> u32 {
+    value.iter().filter(|&&byte| byte >= 0xf0).count() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operations_match_native() {
+        let previous = core::array::from_fn(|i| i as u8 * 17);
+        let value: [u8; 16] = core::array::from_fn(|i| 0xff - i as u8 * 13);
+        let table = [0, 11, 22, 33, 44, 55, 66, 77, 88, 99, 110, 121, 132, 143, 154, 165];
+
+        let native = Simd8x16::from_array(value);
+        let native_previous = Simd8x16::from_array(previous);
+        let native_table = Simd8x16::from_array(table);
+
+        assert_eq!(prev::<1>(value, previous), native.prev::<1>(native_previous).to_array());
+        assert_eq!(prev::<2>(value, previous), native.prev::<2>(native_previous).to_array());
+        assert_eq!(prev::<3>(value, previous), native.prev::<3>(native_previous).to_array());
+        assert_eq!(value.map(|byte| byte >> 4), native.shr::<4>().to_array());
+        assert_eq!(lookup_16(value.map(|byte| byte >> 4), table), native.shr::<4>().lookup_16(native_table).to_array());
+        assert_eq!(lookup_16(value.map(|byte| byte & 0x0f), table), (native & Simd8x16::from(0x0f)).lookup_16(native_table).to_array());
+        assert_eq!(zip(value, previous, u8::saturating_sub), native.saturating_sub(native_previous).to_array());
+        assert_eq!(count_continuations(value), native.count_continuations());
+        assert_eq!(count_four_byte_leads(value), native.count_four_byte_leads());
+
+        let (low, high) = native.interleave(native_previous);
+        assert_eq!(interleave(value, previous), (low.to_array(), high.to_array()));
+        assert_eq!(bitmask(value), native.bitmask());
+    }
+}
@@ -0,0 +1,124 @@
+use super::Simd8x16;
+
+use core::arch::wasm32::*;
+
+pub(super) type Repr = v128;
+
+impl Simd8x16 {
+    #[inline(always)]
+    pub fn splat(value: u8) -> Simd8x16 {
+        Simd8x16::from(u8x16_splat(value))
+    }
+
+    #[inline(always)]
+    pub fn and(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(v128_and(self.value, other.value))
+    }
+
+    #[inline(always)]
+    pub fn or(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(v128_or(self.value, other.value))
+    }
+
+    #[inline(always)]
+    pub fn xor(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(v128_xor(self.value, other.value))
+    }
+
+    #[inline(always)]
+    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 {
+        let c = self.value;
+        let p = previous.value;
+
+        // indices 0 to 15 select from the previous vector, 16 to 31 from this one
+        let r = match N {
+            1 => i8x16_shuffle::<15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30>(p, c),
+            2 => i8x16_shuffle::<14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29>(p, c),
+            3 => i8x16_shuffle::<13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28>(p, c),
+            _ => unreachable!("multibyte sequences look back at most 3 bytes"),
+        };
+
+        Simd8x16::from(r)
+    }
+
+    #[inline(always)]
+    pub fn shr<const N: i32>(&self) -> Simd8x16 {
+        let input = self.value;
+
+        let result = u8x16_shr(input, N as u32);
+
+        Simd8x16::from(result)
+    }
+
+    #[inline(always)]
+    pub fn lookup_16(&self, table: Simd8x16) -> Simd8x16 {
+        let indices = self.value;
+        let tbl = table.value;
+
+        // out of range indices select 0, like pshufb and tbl
+        let result = i8x16_swizzle(tbl, indices);
+
+        Simd8x16::from(result)
+    }
+
+    #[inline(always)]
+    pub fn saturating_sub(&self, other: Simd8x16) -> Simd8x16 {
+        let a = self.value;
+        let b = other.value;
+
+        let result = u8x16_sub_sat(a, b);
+
+        Simd8x16::from(result)
+    }
+
+    #[inline(always)]
+    pub fn interleave(&self, other: Simd8x16) -> (Simd8x16, Simd8x16) {
+        let a = self.value;
+        let b = other.value;
+
+        let low = i8x16_shuffle::<0, 16, 1, 17, 2, 18, 3, 19, 4, 20, 5, 21, 6, 22, 7, 23>(a, b);
+        let high = i8x16_shuffle::<8, 24, 9, 25, 10, 26, 11, 27, 12, 28, 13, 29, 14, 30, 15, 31>(a, b);
+
+        (Simd8x16::from(low), Simd8x16::from(high))
+    }
+
+    #[inline(always)]
+    pub fn bitmask(&self) -> u16 {
+        let input = self.value;
+
+        i8x16_bitmask(input)
+    }
+
+    #[inline(always)]
+    pub fn count_continuations(&self) -> u32 {
+        let input = self.value;
+
+        // continuation bytes are the signed bytes below -64
+        let mask = i8x16_lt(input, i8x16_splat(-0x40));
+
+        i8x16_bitmask(mask).count_ones()
+    }
+
+    #[inline(always)]
+    pub fn count_four_byte_leads(&self) -> u32 {
+        let input = self.value;
+
+        let mask = u8x16_ge(input, u8x16_splat(0xf0));
+
+        i8x16_bitmask(mask).count_ones()
+    }
+}
+
+impl From<Simd8x16> for v128 {
+    #[inline(always)]
+    fn from(value: Simd8x16) -> Self {
+        value.value
+    }
+}
+
+impl From<v128> for Simd8x16 {
+    #[inline(always)]
+    fn from(value: v128) -> Self {
+        Self { value }
+    }
+}
@@ -1,63 +1,128 @@
 use super::Simd8x16;
 
-use core::simd::Simd;
-
 #[cfg(target_arch = "x86")]
 use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
 
+pub(super) type Repr = __m128i;
+
 impl Simd8x16 {
-    #[inline]
-    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 where [(); { 16 - N } as usize]: {
-        let c = __m128i::from(self.value);
-        let p = __m128i::from(previous.value);
+    #[inline(always)]
+    pub fn splat(value: u8) -> Simd8x16 {
+        Simd8x16::from(unsafe { _mm_set1_epi8(value as i8) })
+    }
+
+    #[inline(always)]
+    pub fn and(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(unsafe { _mm_and_si128(self.value, other.value) })
+    }
+
+    #[inline(always)]
+    pub fn or(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(unsafe { _mm_or_si128(self.value, other.value) })
+    }
 
-        let r = unsafe { _mm_alignr_epi8::<{ 16 - N }>(c, p) };
+    #[inline(always)]
+    pub fn xor(self, other: Simd8x16) -> Simd8x16 {
+        Simd8x16::from(unsafe { _mm_xor_si128(self.value, other.value) })
+    }
+
+    #[inline(always)]
+    pub fn prev<const N: i32>(&self, previous: Simd8x16) -> Simd8x16 {
+        let c = self.value;
+        let p = previous.value;
+
+        let r = match N {
+            1 => unsafe { _mm_alignr_epi8::<15>(c, p) },
+            2 => unsafe { _mm_alignr_epi8::<14>(c, p) },
+            3 => unsafe { _mm_alignr_epi8::<13>(c, p) },
+            _ => unreachable!("multibyte sequences look back at most 3 bytes"),
+        };
 
         Simd8x16::from(r)
     }
 
-    #[inline]
-    pub fn shr<const N: i32>(&self) -> Simd8x16 where [(); { 16 - N } as usize]: {
-        let c = __m128i::from(self.value);
+    #[inline(always)]
+    pub fn shr<const N: i32>(&self) -> Simd8x16 {
+        let c = self.value;
 
         let r = unsafe { _mm_srli_epi16::<N>(c) };
 
         Simd8x16::from(r) & Simd8x16::from(0xff >> N)
     }
 
-    #[inline]
+    #[inline(always)]
     pub fn lookup_16(&self, table: Simd8x16) -> Simd8x16 {
-        let c = __m128i::from(self.value);
-        let t = __m128i::from(table.value);
+        let c = self.value;
+        let t = table.value;
 
         let r = unsafe { _mm_shuffle_epi8(t, c) };
 
         Simd8x16::from(r)
     }
 
-    #[inline]
+    #[inline(always)]
     pub fn saturating_sub(&self, other: Simd8x16) -> Simd8x16 {
-        let s = __m128i::from(self.value);
-        let o = __m128i::from(other.value);
+        let s = self.value;
+        let o = other.value;
 
         let r = unsafe { _mm_subs_epu8(s, o) };
 
         Simd8x16::from(r)
     }
+
+    #[inline(always)]
+    pub fn interleave(&self, other: Simd8x16) -> (Simd8x16, Simd8x16) {
+        let a = self.value;
+        let b = other.value;
+
+        let low = unsafe { _mm_unpacklo_epi8(a, b) };
+        let high = unsafe { _mm_unpackhi_epi8(a, b) };
+
+        (Simd8x16::from(low), Simd8x16::from(high))
+    }
+
+    #[inline(always)]
+    pub fn bitmask(&self) -> u16 {
+        let c = self.value;
+
+        let r = unsafe { _mm_movemask_epi8(c) };
+
+        r as u16
+    }
+
+    #[inline(always)]
+    pub fn count_continuations(&self) -> u32 {
+        let c = self.value;
+
+        // continuation bytes are the signed bytes below -64
+        let r = unsafe { _mm_movemask_epi8(_mm_cmplt_epi8(c, _mm_set1_epi8(-0x40))) };
+
+        r.count_ones()
+    }
+
+    #[inline(always)]
+    pub fn count_four_byte_leads(&self) -> u32 {
+        let c = self.value;
+
+        // there is no unsigned comparison, the bytes of at least 0xF0 are those unchanged by the maximum
+        let r = unsafe { _mm_movemask_epi8(_mm_cmpeq_epi8(_mm_max_epu8(c, _mm_set1_epi8(0xf0u8 as i8)), c)) };
+
+        r.count_ones()
+    }
 }
 
 impl From<Simd8x16> for __m128i {
+    #[inline(always)]
     fn from(value: Simd8x16) -> Self {
-        value.value.into()
+        value.value
     }
 }
 
 impl From<__m128i> for Simd8x16 {
+    #[inline(always)]
     fn from(value: __m128i) -> Self {
-        Self {
-            value: Simd::from(value),
-        }
+        Self { value }
     }
-}
\ No newline at end of file
+}
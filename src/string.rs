@@ -0,0 +1,205 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::String;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::{FromSharedUtf8Error, FromUtf8Error, from_utf8, from_utf8_unchecked};
+
+/// The Unicode replacement character U+FFFD.
+const REPLACEMENT: &str = "\u{FFFD}";
+
+/// Converts a slice of bytes to a string, including invalid characters.
+///
+/// Works like [`String::from_utf8_lossy`]: every maximal subpart of an invalid
+/// sequence is replaced with U+FFFD REPLACEMENT CHARACTER, as recommended by
+/// the WHATWG Encoding Standard. Valid input is borrowed without copying.
+///
+/// # Examples
+///
+/// ```rust
+/// let output = utf8simd::from_utf8_lossy(b"Hello \xF0\x90\x80World");
+/// assert_eq!(output, "Hello \u{FFFD}World");
+/// ```
+pub fn from_utf8_lossy(v: &[u8]) -> Cow<'_, str> {
+    let mut err = match from_utf8(v) {
+        Ok(str) => return Cow::Borrowed(str),
+        Err(err) => err,
+    };
+
+    let mut res = String::with_capacity(v.len());
+    let mut rest = v;
+
+    loop {
+        let (valid, invalid) = rest.split_at(err.valid_up_to());
+        res.push_str(unsafe { from_utf8_unchecked(valid) });
+        res.push_str(REPLACEMENT);
+
+        // a truncated sequence can only occur at the end of the input
+        let Some(len) = err.error_len() else {
+            break;
+        };
+
+        // skip ahead to the next valid run
+        rest = &invalid[len..];
+        match from_utf8(rest) {
+            Ok(str) => {
+                res.push_str(str);
+                break;
+            }
+            Err(e) => err = e,
+        }
+    }
+
+    Cow::Owned(res)
+}
+
+/// Converts a vector of bytes to a `String`.
+///
+/// Works like [`String::from_utf8`]: the buffer is reused without copying. On
+/// failure, the error gives back the original buffer together with the
+/// [`Utf8Error`](crate::Utf8Error) describing the invalid sequence.
+///
+/// # Examples
+///
+/// ```rust
+/// let string = utf8simd::from_utf8_vec(b"Hello, world!".to_vec()).unwrap();
+/// assert_eq!(string, "Hello, world!");
+///
+/// let err = utf8simd::from_utf8_vec(b"Hello \xF0\x90\x80World".to_vec()).unwrap_err();
+/// assert_eq!(err.utf8_error().valid_up_to(), 6);
+/// assert_eq!(err.into_bytes(), b"Hello \xF0\x90\x80World");
+/// ```
+pub fn from_utf8_vec(vec: Vec<u8>) -> Result<String, FromUtf8Error> {
+    match from_utf8(&vec) {
+        Ok(_) => Ok(unsafe { String::from_utf8_unchecked(vec) }),
+        Err(err) => Err(FromUtf8Error::new(vec, err)),
+    }
+}
+
+/// Converts a boxed slice of bytes to a boxed string slice.
+///
+/// The allocation is reused without copying. On failure, the error gives back
+/// the original bytes.
+pub fn from_boxed_utf8(v: Box<[u8]>) -> Result<Box<str>, FromUtf8Error> {
+    match from_utf8(&v) {
+        Ok(_) => Ok(unsafe { from_boxed_utf8_unchecked(v) }),
+        Err(err) => Err(FromUtf8Error::new(v.into_vec(), err)),
+    }
+}
+
+/// Converts a boxed slice of bytes to a boxed string slice without checking that the string contains valid UTF-8.
+///
+/// # Safety
+/// The bytes passed in must be valid UTF-8.
+pub unsafe fn from_boxed_utf8_unchecked(v: Box<[u8]>) -> Box<str> {
+    unsafe { Box::from_raw(Box::into_raw(v) as *mut str) }
+}
+
+/// Converts a reference-counted slice of bytes to a reference-counted string slice.
+///
+/// The allocation is shared without copying. On failure, the error gives back
+/// this reference to the bytes.
+pub fn from_rc_utf8(v: Rc<[u8]>) -> Result<Rc<str>, FromSharedUtf8Error<Rc<[u8]>>> {
+    match from_utf8(&v) {
+        // SAFETY: the bytes are valid UTF-8, and a str has the layout of a [u8]
+        Ok(_) => Ok(unsafe { Rc::from_raw(Rc::into_raw(v) as *const str) }),
+        Err(err) => Err(FromSharedUtf8Error::new(v, err)),
+    }
+}
+
+/// Converts an atomically reference-counted slice of bytes to an atomically reference-counted string slice.
+///
+/// The allocation is shared without copying. On failure, the error gives back
+/// this reference to the bytes.
+#[cfg(target_has_atomic = "ptr")]
+pub fn from_arc_utf8(v: Arc<[u8]>) -> Result<Arc<str>, FromSharedUtf8Error<Arc<[u8]>>> {
+    match from_utf8(&v) {
+        // SAFETY: the bytes are valid UTF-8, and a str has the layout of a [u8]
+        Ok(_) => Ok(unsafe { Arc::from_raw(Arc::into_raw(v) as *const str) }),
+        Err(err) => Err(FromSharedUtf8Error::new(v, err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossy_valid_is_borrowed() {
+        let bytes = "Hello, 世界! 🦀".repeat(16);
+        assert!(matches!(from_utf8_lossy(bytes.as_bytes()), Cow::Borrowed(str) if str == bytes));
+    }
+
+    #[test]
+    fn lossy_replaces_maximal_subparts() {
+        let sequences: [&[u8]; 6] = [
+            b"\x80",
+            b"a\xF0\x90\x80b",
+            b"\xC0\xAF\xE0\x80\xBF",
+            b"\xED\xA0\x80\xF4\x90\x80\x80",
+            b"\xF1\x80\x80\xE1\x80\xC2\x61",
+            b"\xE2\x82",
+        ];
+
+        for sequence in sequences {
+            // short input as well as input long enough for the SIMD path
+            for padding in [0, 100] {
+                let mut bytes = alloc::vec![b'a'; padding];
+                bytes.extend_from_slice(sequence);
+                bytes.extend(core::iter::repeat_n(b'z', padding));
+
+                assert_eq!(from_utf8_lossy(&bytes), String::from_utf8_lossy(&bytes));
+            }
+        }
+    }
+
+    #[test]
+    fn vec_roundtrip() {
+        let bytes = "Hello, 世界! 🦀".repeat(16).into_bytes();
+        let ptr = bytes.as_ptr();
+
+        let string = from_utf8_vec(bytes).unwrap();
+        assert_eq!(string.as_ptr(), ptr);
+
+        let mut bytes = string.into_bytes();
+        bytes[100] = 0xFF;
+
+        let err = from_utf8_vec(bytes.clone()).unwrap_err();
+        assert_eq!(err.utf8_error(), crate::from_utf8(&bytes).unwrap_err());
+        assert_eq!(err.into_bytes(), bytes);
+    }
+
+    #[test]
+    fn shared_conversions() {
+        let text = "Hello, 世界! 🦀".repeat(16);
+
+        let boxed = from_boxed_utf8(text.as_bytes().into()).unwrap();
+        assert_eq!(&*boxed, text);
+
+        let rc = from_rc_utf8(text.as_bytes().into()).unwrap();
+        assert_eq!(&*rc, text);
+
+        let arc = from_arc_utf8(text.as_bytes().into()).unwrap();
+        assert_eq!(&*arc, text);
+
+        let mut bytes = text.into_bytes();
+        bytes[0] = 0x80;
+
+        let err = from_boxed_utf8(bytes.clone().into_boxed_slice()).unwrap_err();
+        assert_eq!(err.as_bytes(), bytes);
+
+        // the error holds the caller's reference, not a copy of the bytes
+        let rc: Rc<[u8]> = bytes.as_slice().into();
+        let rc_err = from_rc_utf8(rc.clone()).unwrap_err();
+        assert_eq!(rc_err.utf8_error(), err.utf8_error());
+        assert!(Rc::ptr_eq(&rc_err.into_inner(), &rc));
+
+        let arc: Arc<[u8]> = bytes.as_slice().into();
+        let arc_err = from_arc_utf8(arc.clone()).unwrap_err();
+        assert_eq!(arc_err.as_bytes(), bytes);
+        assert!(Arc::ptr_eq(&arc_err.into_inner(), &arc));
+    }
+}
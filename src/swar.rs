@@ -0,0 +1,201 @@
+use crate::validator::{COUNT_CHARS, COUNT_NONE, COUNT_UTF16};
+use crate::{utf8, Utf8Error};
+
+/// Number of bytes per word.
+const WORD: usize = size_of::<usize>();
+
+/// The high bit of every byte in a word.
+const HIGH_BITS: usize = usize::from_ne_bytes([0x80; WORD]);
+
+// byte classes
+const ASCII: u8 = 0; // 00..7F
+const CONT_LOW: u8 = 1; // 80..8F
+const CONT_MID: u8 = 2; // 90..9F
+const CONT_HIGH: u8 = 3; // A0..BF
+const INVALID: u8 = 4; // C0..C1, F5..FF
+const LEAD_2: u8 = 5; // C2..DF
+const LEAD_E0: u8 = 6; // E0
+const LEAD_3: u8 = 7; // E1..EC, EE..EF
+const LEAD_ED: u8 = 8; // ED
+const LEAD_F0: u8 = 9; // F0
+const LEAD_4: u8 = 10; // F1..F3
+const LEAD_F4: u8 = 11; // F4
+
+// states, named after the bytes that are still expected
+const ACCEPT: u8 = 0;
+const ONE: u8 = 1; // 80..BF
+const TWO: u8 = 2; // 80..BF 80..BF
+const TWO_E0: u8 = 3; // A0..BF 80..BF
+const TWO_ED: u8 = 4; // 80..9F 80..BF
+const THREE: u8 = 5; // 80..BF 80..BF 80..BF
+const THREE_F0: u8 = 6; // 90..BF 80..BF 80..BF
+const THREE_F4: u8 = 7; // 80..8F 80..BF 80..BF
+const REJECT: u8 = 8;
+
+/// The class of every byte.
+static CLASSES: [u8; 256] = {
+    let mut classes = [INVALID; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        classes[byte] = match byte {
+            0x00..=0x7f => ASCII,
+            0x80..=0x8f => CONT_LOW,
+            0x90..=0x9f => CONT_MID,
+            0xa0..=0xbf => CONT_HIGH,
+            0xc2..=0xdf => LEAD_2,
+            0xe0 => LEAD_E0,
+            0xed => LEAD_ED,
+            0xe1..=0xef => LEAD_3,
+            0xf0 => LEAD_F0,
+            0xf1..=0xf3 => LEAD_4,
+            0xf4 => LEAD_F4,
+            _ => INVALID,
+        };
+        byte += 1;
+    }
+    classes
+};
+
+/// The next state for every state and byte class.
+static TRANSITIONS: [[u8; 12]; 9] = {
+    const R: u8 = REJECT;
+    [
+        // ASCII   CONT_LOW  CONT_MID  CONT_HIGH INVALID LEAD_2 LEAD_E0 LEAD_3 LEAD_ED LEAD_F0   LEAD_4 LEAD_F4
+        [ACCEPT, R, R, R, R, ONE, TWO_E0, TWO, TWO_ED, THREE_F0, THREE, THREE_F4], // ACCEPT
+        [R, ACCEPT, ACCEPT, ACCEPT, R, R, R, R, R, R, R, R], // ONE
+        [R, ONE, ONE, ONE, R, R, R, R, R, R, R, R], // TWO
+        [R, R, R, ONE, R, R, R, R, R, R, R, R], // TWO_E0
+        [R, ONE, ONE, R, R, R, R, R, R, R, R, R], // TWO_ED
+        [R, TWO, TWO, TWO, R, R, R, R, R, R, R, R], // THREE
+        [R, R, TWO, TWO, R, R, R, R, R, R, R, R], // THREE_F0
+        [R, TWO, R, R, R, R, R, R, R, R, R, R], // THREE_F4
+        [R, R, R, R, R, R, R, R, R, R, R, R], // REJECT
+    ]
+};
+
+/// Validates a slice a word at a time, for targets without SIMD instructions.
+///
+/// Runs of ASCII words are skipped with a single test per word, all other
+/// bytes go through a DFA over byte classes.
+pub(crate) fn validate(v: &[u8]) -> Result<(), Utf8Error> {
+    run::<COUNT_NONE>(v)?;
+    Ok(())
+}
+
+/// Validates a slice a word at a time and counts what `COUNT` selects, see [`validate`].
+pub(crate) fn count<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    run::<COUNT>(v)
+}
+
+/// Runs the DFA over a slice, returning the count selected by `COUNT`, or 0 for [`COUNT_NONE`].
+#[inline(always)]
+fn run<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    let mut state = ACCEPT;
+
+    // counted bytes, which only occur outside of ASCII words
+    let mut continuations = 0;
+    let mut four_byte_leads = 0;
+
+    // start of the current sequence
+    let mut start = 0;
+
+    let mut i = 0;
+    while i < v.len() {
+        if state == ACCEPT {
+            // fast path for ASCII-only words
+            while i + WORD <= v.len() && is_ascii(&v[i..i + WORD]) {
+                i += WORD;
+            }
+
+            if i == v.len() {
+                break;
+            }
+
+            start = i;
+        }
+
+        state = TRANSITIONS[state as usize][CLASSES[v[i] as usize] as usize];
+        if state == REJECT {
+            return Err(utf8::locate_error(v, start));
+        }
+
+        if COUNT != COUNT_NONE && utf8::is_continuation(v[i]) {
+            continuations += 1;
+        }
+        if COUNT == COUNT_UTF16 && v[i] >= 0xf0 {
+            four_byte_leads += 1;
+        }
+
+        i += 1;
+    }
+
+    // any incomplete sequence at the end of the input is an error
+    if state != ACCEPT {
+        return Err(utf8::locate_error(v, start));
+    }
+
+    Ok(match COUNT {
+        COUNT_CHARS => v.len() - continuations,
+        COUNT_UTF16 => v.len() - continuations + four_byte_leads,
+        _ => 0,
+    })
+}
+
+/// Checks a word for non-ASCII bytes.
+#[inline(always)]
+fn is_ascii(word: &[u8]) -> bool {
+    let word = usize::from_ne_bytes(word.try_into().unwrap());
+    word & HIGH_BITS == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Validates and counts `v` with both the SWAR and the standard library validator.
+    fn check(v: &[u8]) {
+        let expected = core::str::from_utf8(v).map_err(|err| Utf8Error::from_core(err, v));
+        assert_eq!(validate(v), expected.map(|_| ()), "{v:x?}");
+        assert_eq!(count::<COUNT_CHARS>(v), expected.map(|str| str.chars().count()), "{v:x?}");
+        assert_eq!(count::<COUNT_UTF16>(v), expected.map(|str| str.encode_utf16().count()), "{v:x?}");
+    }
+
+    #[test]
+    fn valid_utf8() {
+        check(b"");
+        check(b"Hello, world!");
+        check(crate::fixtures::TEXT.as_bytes());
+        check("\u{7f}\u{80}\u{7ff}\u{800}\u{d7ff}\u{e000}\u{ffff}\u{10000}\u{10ffff}".as_bytes());
+    }
+
+    #[test]
+    fn all_sequences() {
+        // every sequence of up to 3 bytes, surrounded by ASCII words
+        let bytes = [0x00, 0x41, 0x7f, 0x80, 0x8f, 0x90, 0x9f, 0xa0, 0xbf, 0xc0, 0xc1, 0xc2, 0xdf, 0xe0, 0xe1, 0xec, 0xed, 0xee, 0xef, 0xf0, 0xf1, 0xf3, 0xf4, 0xf5, 0xff];
+
+        for a in bytes {
+            for b in bytes {
+                for c in bytes {
+                    let mut buf = [b'a'; 24];
+                    buf[9..12].copy_from_slice(&[a, b, c]);
+                    check(&buf);
+                    check(&buf[..12]);
+                    check(&buf[..11]);
+                    check(&buf[9..12]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn four_byte_sequences() {
+        for lead in [0xf0, 0xf1, 0xf4, 0xf5] {
+            for second in [0x80, 0x8f, 0x90, 0xbf] {
+                let mut buf = [b'a'; 32];
+                buf[13..17].copy_from_slice(&[lead, second, 0x80, 0xbf]);
+                check(&buf);
+                check(&buf[..16]);
+            }
+        }
+    }
+}
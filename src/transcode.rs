@@ -0,0 +1,438 @@
+use crate::simd::{Simd8x16, Vector};
+use crate::utf8::is_continuation;
+use crate::validator::COUNT_NONE;
+use crate::{Utf8Error, Utf8Validator};
+
+/// A code unit that validated UTF-8 is decoded to.
+pub(crate) trait Unit: Copy {
+    /// Whether chars outside the Basic Multilingual Plane are split into a surrogate pair
+    const PAIRS: bool;
+
+    /// Convert a scalar value below U+10000 or a surrogate with `PAIRS`, any scalar value otherwise
+    ///
+    /// # Safety
+    /// `c` must be a Unicode scalar value, or a surrogate with `PAIRS`.
+    unsafe fn from_scalar(c: u32) -> Self;
+
+    /// Store 8 little-endian UTF-16 code units from a vector
+    fn store(units: Simd8x16, dst: &mut [Self; 8]);
+}
+
+impl Unit for u16 {
+    const PAIRS: bool = true;
+
+    #[inline(always)]
+    unsafe fn from_scalar(c: u32) -> Self {
+        c as u16
+    }
+
+    #[inline(always)]
+    fn store(units: Simd8x16, dst: &mut [Self; 8]) {
+        // SAFETY: any 16 bytes are 8 valid u16s
+        let units = unsafe { core::mem::transmute::<[u8; 16], [u16; 8]>(units.to_array()) };
+        *dst = units.map(u16::from_le);
+    }
+}
+
+impl Unit for u32 {
+    const PAIRS: bool = false;
+
+    #[inline(always)]
+    unsafe fn from_scalar(c: u32) -> Self {
+        c
+    }
+
+    #[inline(always)]
+    fn store(units: Simd8x16, dst: &mut [Self; 8]) {
+        // widen each half with a shuffle, the indices with the high bit set select zeros
+        let (low, high) = dst.split_at_mut(4);
+        for (dst, widen) in [(low, WIDEN_LOW), (high, WIDEN_HIGH)] {
+            // SAFETY: any 16 bytes are 4 valid u32s
+            let units = unsafe { core::mem::transmute::<[u8; 16], [u32; 4]>(Simd8x16::from_array(widen).lookup_16(units).to_array()) };
+            for (unit, value) in dst.iter_mut().zip(units) {
+                *unit = u32::from_le(value);
+            }
+        }
+    }
+}
+
+/// Shuffle that widens the first 4 UTF-16 code units of a vector to 32 bits.
+const WIDEN_LOW: [u8; 16] = [0, 1, 0x80, 0x80, 2, 3, 0x80, 0x80, 4, 5, 0x80, 0x80, 6, 7, 0x80, 0x80];
+
+/// Shuffle that widens the last 4 UTF-16 code units of a vector to 32 bits.
+const WIDEN_HIGH: [u8; 16] = [8, 9, 0x80, 0x80, 10, 11, 0x80, 0x80, 12, 13, 0x80, 0x80, 14, 15, 0x80, 0x80];
+
+/// For every mask of 8 UTF-16 code units, the shuffle that moves the selected ones to the front and zeros the rest.
+const COMPRESS: [[u8; 16]; 256] = {
+    let mut table = [[0x80; 16]; 256];
+
+    let mut mask = 0;
+    while mask < 256 {
+        let mut selected = 0;
+        let mut unit = 0;
+        while unit < 8 {
+            if mask & 1 << unit != 0 {
+                table[mask][2 * selected] = 2 * unit as u8;
+                table[mask][2 * selected + 1] = 2 * unit as u8 + 1;
+                selected += 1;
+            }
+            unit += 1;
+        }
+        mask += 1;
+    }
+
+    table
+};
+
+/// Lookup table by high nibble, 0xFF for continuation bytes.
+const CONTINUATION: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+
+/// Lookup table for the bits 6 and 7 of a code unit, from the 2 lowest bits of the byte before the last one.
+const SHL_6: [u8; 16] = [0x00, 0x40, 0x80, 0xc0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// Lookup table for the bits 12 to 15 of a code unit, from the 4 lowest bits of the lead byte of a 3-byte sequence.
+const SHL_4: [u8; 16] = [0x00, 0x10, 0x20, 0x30, 0x40, 0x50, 0x60, 0x70, 0x80, 0x90, 0xa0, 0xb0, 0xc0, 0xd0, 0xe0, 0xf0];
+
+/// Whether [`decode_simd`] decodes blocks with vectors, which the scalar backend only emulates.
+const BLOCKS: bool = !cfg!(utf8simd_scalar);
+
+/// Decodes UTF-8 to UTF-16 with the best kernel for the CPU, see [`decode_simd`].
+#[inline]
+pub(crate) fn decode_utf16(src: &[u8], dst: &mut [u16]) -> Result<usize, Utf8Error> {
+    #[cfg(utf8simd_dispatch)]
+    return crate::dispatch::decode_utf16(src, dst);
+
+    #[cfg(not(utf8simd_dispatch))]
+    decode_simd::<crate::simd::Native, u16>(src, dst)
+}
+
+/// Decodes UTF-8 to UTF-32 with the best kernel for the CPU, see [`decode_simd`].
+///
+/// Only scalar values are written to `dst`, so it may also be a slice of chars.
+#[inline]
+pub(crate) fn decode_utf32(src: &[u8], dst: &mut [u32]) -> Result<usize, Utf8Error> {
+    #[cfg(utf8simd_dispatch)]
+    return crate::dispatch::decode_utf32(src, dst);
+
+    #[cfg(not(utf8simd_dispatch))]
+    decode_simd::<crate::simd::Native, u32>(src, dst)
+}
+
+/// Decodes UTF-8 to code units, validating it in the same pass with vectors of type `V`.
+///
+/// The input is validated in 64-byte chunks like with [`Utf8Validator`], ASCII
+/// chunks are widened directly and all other chunks are decoded once they have
+/// been validated, 16 bytes at a time with vectors unless they contain chars
+/// outside the Basic Multilingual Plane. Returns the number of code units
+/// written.
+///
+/// Always inlined, see [`Vector`].
+#[inline(always)]
+pub(crate) fn decode_simd<V: Vector, U: Unit>(src: &[u8], dst: &mut [U]) -> Result<usize, Utf8Error> {
+    let mut validator = Utf8Validator::new();
+    let mut transcoder = Transcoder::<U, BLOCKS>::new(src, dst);
+
+    let mut chunks = src.chunks_exact(64);
+    for (i, chunk) in (&mut chunks).enumerate() {
+        let chunk: &[u8; 64] = chunk.try_into().unwrap();
+        let counts = validator.process::<V, COUNT_NONE>(chunk, 64)?;
+
+        // ASCII cannot follow an incomplete sequence, so the chunk starts with a
+        // new char, unless the blocks left chars of earlier chunks for later
+        if counts.ascii && transcoder.read == i * 64 {
+            transcoder.widen(chunk);
+        } else {
+            transcoder.decode_blocks((i + 1) * 64);
+        }
+    }
+
+    // the remainder, padded with zeros that are not transcoded
+    let remainder = chunks.remainder();
+    let mut padded = [0u8; 64];
+    padded[..remainder.len()].copy_from_slice(remainder);
+    validator.process::<V, COUNT_NONE>(&padded, remainder.len())?;
+
+    // check for incomplete bytes
+    validator.finish()?;
+
+    transcoder.decode(src.len());
+    Ok(transcoder.finish())
+}
+
+/// Decodes UTF-8 to code units after validating it as a whole, for CPUs without the byte shuffles of the validator.
+#[cfg(utf8simd_dispatch)]
+pub(crate) fn decode_scalar<U: Unit>(src: &[u8], dst: &mut [U]) -> Result<usize, Utf8Error> {
+    core::str::from_utf8(src).map_err(|err| Utf8Error::from_core(err, src))?;
+
+    let mut transcoder = Transcoder::<U, false>::new(src, dst);
+    transcoder.decode(src.len());
+    Ok(transcoder.finish())
+}
+
+/// Writes the code units of validated UTF-8, with the vector operations of [`Simd8x16`] if `SIMD`.
+///
+/// Running out of space in `dst` only stops the writing, the panic is left to
+/// [`finish`](Self::finish) so that an error later in the input is still
+/// returned instead.
+struct Transcoder<'a, U, const SIMD: bool> {
+    src: &'a [u8],
+    dst: &'a mut [U],
+    /// Start of the next char in `src`
+    read: usize,
+    /// Number of code units in `dst`
+    written: usize,
+    /// Whether a char did not fit into `dst`
+    short: bool,
+}
+
+impl<'a, U: Unit, const SIMD: bool> Transcoder<'a, U, SIMD> {
+    #[inline(always)]
+    fn new(src: &'a [u8], dst: &'a mut [U]) -> Self {
+        Transcoder { src, dst, read: 0, written: 0, short: false }
+    }
+
+    /// Returns the number of code units written once the whole input was valid, panics if they did not fit.
+    #[inline(always)]
+    fn finish(self) -> usize {
+        if self.short {
+            too_short();
+        }
+
+        self.written
+    }
+
+    /// Widens an ASCII chunk that starts at the next char.
+    #[inline(always)]
+    fn widen(&mut self, chunk: &[u8; 64]) {
+        let Some(dst) = self.dst.get_mut(self.written..self.written + 64) else {
+            // convert the chars that still fit
+            return self.decode(self.read + 64);
+        };
+
+        // interleave the bytes with zeros for the UTF-16 code units
+        for (dst, block) in dst.chunks_exact_mut(16).zip(chunk.chunks_exact(16)) {
+            let (low, high) = Simd8x16::from_array(block.try_into().unwrap()).interleave(Simd8x16::splat(0));
+            U::store(low, (&mut dst[..8]).try_into().unwrap());
+            U::store(high, (&mut dst[8..]).try_into().unwrap());
+        }
+
+        self.read += 64;
+        self.written += 64;
+    }
+
+    /// Decodes the validated chars before `end`, a char that continues after it is left for later.
+    #[inline(always)]
+    fn decode(&mut self, end: usize) {
+        if self.short {
+            return;
+        }
+
+        // a char has at most as many code units as UTF-8 bytes
+        if self.dst.len() - self.written >= end - self.read {
+            self.decode_blocks_into(end);
+            self.decode_chars::<false>(end);
+        } else {
+            self.decode_chars::<true>(end);
+        }
+    }
+
+    /// Decodes the validated chars before `end` in blocks, the chars after the last block are left for later.
+    ///
+    /// Decoding them one by one is left to the next call, where they start a
+    /// block of the next chunk instead.
+    #[inline(always)]
+    fn decode_blocks(&mut self, end: usize) {
+        if !SIMD {
+            return self.decode(end);
+        }
+
+        if self.short {
+            return;
+        }
+
+        if self.dst.len() - self.written >= end - self.read {
+            self.decode_blocks_into(end);
+        } else {
+            self.decode_chars::<true>(end);
+        }
+    }
+
+    /// Decodes the validated chars before `end` in blocks of 16 bytes, with enough space left in `dst`.
+    #[inline(always)]
+    fn decode_blocks_into(&mut self, end: usize) {
+        // the blocks follow each other regardless of the chars in them, a
+        // block decodes the chars that end in it, so the byte after it must be
+        // validated as well
+        let mut block = self.read;
+        while SIMD && block + 17 <= end {
+            if self.decode_block(block) {
+                block += 16;
+                continue;
+            }
+
+            // chars outside the BMP are decoded one by one, from the char the block starts in
+            self.read = self.char_start(block);
+            self.decode_chars::<false>(block + 16);
+            block = self.read;
+        }
+
+        self.read = self.char_start(block);
+    }
+
+    /// The start of the char that `position` is in.
+    #[inline(always)]
+    fn char_start(&self, mut position: usize) -> usize {
+        while position > self.read && is_continuation(self.src[position]) {
+            position -= 1;
+        }
+
+        position
+    }
+
+    /// Decodes the chars that end in the 16 bytes at `position` with vectors, unless one of them is outside the BMP.
+    ///
+    /// The chars that end before `position` must have been decoded. Needs room
+    /// for 16 code units, and the byte after the block to find the chars that
+    /// end in it.
+    #[inline(always)]
+    fn decode_block(&mut self, position: usize) -> bool {
+        let src = &self.src[position.saturating_sub(3)..position + 17];
+
+        // SAFETY: the loads start at most 3 bytes into `src`, which is at least 17 bytes long
+        let load = |i: usize| unsafe { Simd8x16::load(src.as_ptr().add(i)) };
+
+        // the bytes before the block, which is only at the start of the input if that starts a char
+        let zero = Simd8x16::splat(0);
+        let (block, prev1, prev2, prev3) = match position {
+            0..3 => {
+                let block = load(position);
+                (block, block.prev::<1>(zero), block.prev::<2>(zero), block.prev::<3>(zero))
+            }
+            _ => (load(3), load(2), load(1), load(0)),
+        };
+
+        // surrogate pairs are left to the scalar code, including one that ends in the block
+        let four_byte_leads = block.saturating_sub(Simd8x16::splat(0xef)) | prev3.saturating_sub(Simd8x16::splat(0xef));
+        if !four_byte_leads.is_zero() {
+            return false;
+        }
+
+        // every byte is decoded as the last one of a char, with the payload of the bytes before it
+        let continuation = block.shr::<4>().lookup_16(Simd8x16::from_array(CONTINUATION));
+        let previous_continuation = prev1.shr::<4>().lookup_16(Simd8x16::from_array(CONTINUATION));
+
+        // the low byte of ASCII is the byte itself, all other chars keep 6 bits of it and get 2 from the byte before
+        let low = (block & (Simd8x16::splat(0x7f) ^ (continuation & Simd8x16::splat(0x40))))
+            | ((prev1 & Simd8x16::splat(0x03)).lookup_16(Simd8x16::from_array(SHL_6)) & continuation);
+
+        // the high byte has 3 bits of a 2-byte lead, or 4 bits of the continuation byte and the 3-byte lead before it
+        let high = continuation
+            & ((prev1.shr::<2>() & (Simd8x16::splat(0x07) | (previous_continuation & Simd8x16::splat(0x08))))
+                | ((prev2 & Simd8x16::splat(0x0f)).lookup_16(Simd8x16::from_array(SHL_4)) & previous_continuation));
+
+        // a char ends before every byte that is not a continuation byte
+        let ends = !(continuation.bitmask() >> 1 | (is_continuation(src[src.len() - 1]) as u16) << 15);
+
+        let (first, second) = low.interleave(high);
+        self.compress(first, ends as u8);
+        self.compress(second, (ends >> 8) as u8);
+        true
+    }
+
+    /// Appends the code units selected by `mask` from 8 code units, writing all 8 to `dst`.
+    #[inline(always)]
+    fn compress(&mut self, units: Simd8x16, mask: u8) {
+        let selected = Simd8x16::from_array(COMPRESS[mask as usize]).lookup_16(units);
+
+        // SAFETY: the caller made sure that `dst` has room for the 16 code units of a block
+        let dst = unsafe { &mut *self.dst.as_mut_ptr().add(self.written).cast::<[U; 8]>() };
+        U::store(selected, dst);
+        self.written += mask.count_ones() as usize;
+    }
+
+    /// Decodes the validated chars before `end` one by one, checking the space left in `dst` with `CHECKED`.
+    #[inline(always)]
+    fn decode_chars<const CHECKED: bool>(&mut self, end: usize) {
+        let src = self.src;
+
+        while self.read < end && !(CHECKED && self.short) {
+            let lead = src[self.read];
+
+            if lead < 0x80 {
+                // runs of ASCII in mostly non-ASCII text
+                if !CHECKED && self.read + 8 <= end {
+                    let word = u64::from_ne_bytes(src[self.read..self.read + 8].try_into().unwrap());
+                    if word & 0x8080_8080_8080_8080 == 0 {
+                        for (unit, &byte) in self.dst[self.written..self.written + 8].iter_mut().zip(&src[self.read..]) {
+                            *unit = ascii(byte);
+                        }
+                        self.read += 8;
+                        self.written += 8;
+                        continue;
+                    }
+                }
+
+                self.push::<CHECKED>(lead as u32);
+                self.read += 1;
+                continue;
+            }
+
+            let width = match lead {
+                0x80..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+
+            if self.read + width > end {
+                break;
+            }
+
+            // SAFETY: the sequence ends before `end`, which is at most the length of `src`
+            let byte = |i: usize| unsafe { *src.get_unchecked(self.read + i) as u32 };
+
+            match width {
+                2 => self.push::<CHECKED>((lead as u32 & 0x1f) << 6 | (byte(1) & 0x3f)),
+                3 => self.push::<CHECKED>((lead as u32 & 0x0f) << 12 | (byte(1) & 0x3f) << 6 | (byte(2) & 0x3f)),
+                _ => {
+                    let c = (lead as u32 & 0x07) << 18 | (byte(1) & 0x3f) << 12 | (byte(2) & 0x3f) << 6 | (byte(3) & 0x3f);
+                    if U::PAIRS {
+                        self.push::<CHECKED>(0xd800 | (c - 0x10000) >> 10);
+                        self.push::<CHECKED>(0xdc00 | (c & 0x3ff));
+                    } else {
+                        self.push::<CHECKED>(c);
+                    }
+                }
+            }
+
+            self.read += width;
+        }
+    }
+
+    /// Appends the code unit of a scalar value or surrogate, checking the space left with `CHECKED`.
+    #[inline(always)]
+    fn push<const CHECKED: bool>(&mut self, c: u32) {
+        if CHECKED && (self.short || self.written == self.dst.len()) {
+            self.short = true;
+            return;
+        }
+
+        // SAFETY: the input is valid UTF-8, so `c` is a scalar value, or a
+        // surrogate for units that have them. Without `CHECKED`, the caller
+        // made sure that all code units fit
+        unsafe { *self.dst.get_unchecked_mut(self.written) = U::from_scalar(c) };
+        self.written += 1;
+    }
+}
+
+/// The code unit of an ASCII byte.
+#[inline(always)]
+fn ascii<U: Unit>(byte: u8) -> U {
+    // SAFETY: every ASCII byte is a scalar value
+    unsafe { U::from_scalar(byte as u32) }
+}
+
+#[cold]
+#[track_caller]
+pub(crate) fn too_short() -> ! {
+    panic!("destination buffer is too short for the converted text");
+}
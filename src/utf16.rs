@@ -0,0 +1,583 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::simd::Simd8x16;
+use crate::transcode::{self, too_short};
+use crate::{Utf16Error, Utf8Error};
+
+/// Converts UTF-8 to UTF-16, validating it in the same pass.
+///
+/// Writes the UTF-16 code units of `src` to the start of `dst` and returns
+/// their number. The input is validated in 64-byte chunks like with
+/// [`Utf8Validator`](crate::Utf8Validator), using the widest SIMD
+/// instructions of the CPU, and ASCII chunks are widened directly. All other
+/// chunks are decoded once they have been validated, 16 bytes at a time with
+/// SIMD shuffles, while chars outside the Basic Multilingual Plane are decoded
+/// one by one. The exact length that `dst` needs is returned by
+/// [`utf16_len`](crate::utf16_len), the length of `src` is always enough.
+///
+/// # Errors
+/// Fails on invalid UTF-8 with the same error as [`from_utf8`](crate::from_utf8).
+/// The contents of `dst` are unspecified then.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text. Invalid input is
+/// always reported as an error, even if `dst` is full before the error.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut utf16 = [0u16; 16];
+/// let len = utf8simd::utf8_to_utf16("Grüße, 🦀!".as_bytes(), &mut utf16).unwrap();
+/// assert_eq!(utf16[..len], ['G' as u16, 'r' as u16, 'ü' as u16, 'ß' as u16, 'e' as u16, ',' as u16, ' ' as u16, 0xD83E, 0xDD80, '!' as u16]);
+///
+/// let err = utf8simd::utf8_to_utf16(b"Gr\xFC\xDFe", &mut utf16).unwrap_err();
+/// assert_eq!(err.valid_up_to(), 2);
+/// ```
+pub fn utf8_to_utf16(src: &[u8], dst: &mut [u16]) -> Result<usize, Utf8Error> {
+    transcode::decode_utf16(src, dst)
+}
+
+/// Converts UTF-8 to a vector of UTF-16 code units, validating it in the same pass.
+///
+/// See [`utf8_to_utf16`]. The vector is allocated with the length of `src`,
+/// which is enough for any valid input.
+///
+/// # Examples
+///
+/// ```rust
+/// let utf16 = utf8simd::utf8_to_utf16_vec("Grüße, 世界!".as_bytes()).unwrap();
+/// assert_eq!(utf16, "Grüße, 世界!".encode_utf16().collect::<Vec<_>>());
+/// ```
+#[cfg(feature = "alloc")]
+pub fn utf8_to_utf16_vec(src: &[u8]) -> Result<Vec<u16>, Utf8Error> {
+    let mut dst = alloc::vec![0; src.len()];
+    let len = utf8_to_utf16(src, &mut dst)?;
+    dst.truncate(len);
+    Ok(dst)
+}
+
+/// Converts UTF-16 to UTF-8, failing on unpaired surrogates.
+///
+/// Writes the UTF-8 encoding of `src` to the start of `dst` and returns the
+/// number of bytes written. Blocks of ASCII code units are narrowed directly,
+/// and blocks of 8 code units without surrogates are encoded with SIMD
+/// shuffles. All other code units are encoded one by one, combining surrogate
+/// pairs. At most 3 bytes per code unit are needed.
+///
+/// # Errors
+/// Fails at the first surrogate that is not part of a pair, see
+/// [`Utf16Error`]. It reports the position like [`Utf8Error`], counted in
+/// code units. `dst` holds the conversion of the code units before it then.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text. Invalid code units
+/// are always reported as an error, even if `dst` is full before them.
+///
+/// # Examples
+///
+/// ```rust
+/// let utf16: Vec<u16> = "Grüße, 🦀!".encode_utf16().collect();
+///
+/// let mut utf8 = [0u8; 32];
+/// let len = utf8simd::utf16_to_utf8(&utf16, &mut utf8).unwrap();
+/// assert_eq!(&utf8[..len], "Grüße, 🦀!".as_bytes());
+///
+/// // a low surrogate without a high one
+/// let err = utf8simd::utf16_to_utf8(&[0x61, 0xDC00, 0x62], &mut utf8).unwrap_err();
+/// assert_eq!(err.valid_up_to(), 1);
+/// assert_eq!(err.error_len(), Some(1));
+/// ```
+pub fn utf16_to_utf8(src: &[u16], dst: &mut [u8]) -> Result<usize, Utf16Error> {
+    #[cfg(utf8simd_dispatch)]
+    return crate::dispatch::encode_utf16(src, dst);
+
+    #[cfg(not(utf8simd_dispatch))]
+    encode::<true, SHUFFLES>(src, dst)
+}
+
+/// Converts UTF-16 to UTF-8, replacing unpaired surrogates.
+///
+/// Works like [`utf16_to_utf8`], but every surrogate that is not part of a
+/// pair is replaced with U+FFFD REPLACEMENT CHARACTER, like
+/// `String::from_utf16_lossy` does.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut utf8 = [0u8; 32];
+/// let len = utf8simd::utf16_to_utf8_lossy(&[0x61, 0xD83E, 0x62], &mut utf8);
+/// assert_eq!(&utf8[..len], "a\u{FFFD}b".as_bytes());
+/// ```
+pub fn utf16_to_utf8_lossy(src: &[u16], dst: &mut [u8]) -> usize {
+    #[cfg(utf8simd_dispatch)]
+    let result = crate::dispatch::encode_utf16_lossy(src, dst);
+
+    #[cfg(not(utf8simd_dispatch))]
+    let result = encode::<false, SHUFFLES>(src, dst);
+
+    match result {
+        Ok(written) => written,
+        Err(_) => unreachable!("unpaired surrogates are replaced"),
+    }
+}
+
+/// Whether [`encode`] uses byte shuffles without dispatch, which need SSSE3 on
+/// x86 and which the scalar backend only emulates.
+#[cfg(not(utf8simd_dispatch))]
+const SHUFFLES: bool = !cfg!(utf8simd_scalar) && (cfg!(target_feature = "ssse3") || !cfg!(any(target_arch = "x86", target_arch = "x86_64")));
+
+/// Encodes UTF-16 to UTF-8, failing at the first unpaired surrogate with
+/// `STRICT` and replacing them otherwise. Returns the number of bytes written.
+///
+/// With `SIMD`, blocks of chars below U+10000 are encoded with byte shuffles,
+/// which need SSSE3 on x86. Always inlined, see [`Vector`](crate::simd::Vector).
+#[inline(always)]
+pub(crate) fn encode<const STRICT: bool, const SIMD: bool>(src: &[u16], dst: &mut [u8]) -> Result<usize, Utf16Error> {
+    let mut encoder = Encoder { src, dst, read: 0, written: 0 };
+    encoder.encode::<STRICT, SIMD>()?;
+    Ok(encoder.written)
+}
+
+/// Writes the UTF-8 encoding of UTF-16 code units.
+struct Encoder<'a> {
+    src: &'a [u16],
+    dst: &'a mut [u8],
+    /// Next code unit in `src`
+    read: usize,
+    /// Number of bytes in `dst`
+    written: usize,
+}
+
+impl Encoder<'_> {
+    /// Encodes all code units, see [`encode`].
+    #[inline(always)]
+    fn encode<const STRICT: bool, const SIMD: bool>(&mut self) -> Result<(), Utf16Error> {
+        let src = self.src;
+
+        while self.read < src.len() {
+            // blocks of ASCII are narrowed unit by unit, without encoding chars
+            if let (Some(block), Some(dst)) = (src.get(self.read..self.read + 16), self.dst.get_mut(self.written..self.written + 16))
+                && block.iter().fold(0, |any, &unit| any | unit) < 0x80
+            {
+                for (byte, &unit) in dst.iter_mut().zip(block) {
+                    *byte = unit as u8;
+                }
+
+                self.read += 16;
+                self.written += 16;
+                continue;
+            }
+
+            // blocks without surrogates hold 8 chars of 1 to 3 bytes, at most 24 bytes that are stored as 2 vectors
+            if SIMD
+                && let (Some(block), Some(dst)) = (src.get(self.read..self.read + 8), self.dst.get_mut(self.written..self.written + 28))
+                && block.iter().fold(0, |any, &unit| any | (unit & 0xf800 == 0xd800) as u8) == 0
+            {
+                self.written += encode_bmp(block.try_into().unwrap(), dst.try_into().unwrap());
+                self.read += 8;
+                continue;
+            }
+
+            // a surrogate pair may continue after the block
+            let end = (self.read + 8).min(src.len());
+            while self.read < end {
+                self.encode_char::<STRICT>()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the char at the next code unit.
+    #[inline(always)]
+    fn encode_char<const STRICT: bool>(&mut self) -> Result<(), Utf16Error> {
+        let unit = self.src[self.read];
+
+        let (c, width) = match unit {
+            0xd800..=0xdbff => match self.src.get(self.read + 1) {
+                Some(&low @ 0xdc00..=0xdfff) => (char::from_u32(0x10000 + ((unit as u32 & 0x3ff) << 10 | (low as u32 & 0x3ff))), 2),
+                _ => (None, 1),
+            },
+            // `None` for low surrogates
+            _ => (char::from_u32(unit as u32), 1),
+        };
+
+        let c = match c {
+            Some(c) => c,
+            None if STRICT => {
+                // a high surrogate at the end may be completed by more input
+                let error_len = if unit < 0xdc00 && self.read + 1 == self.src.len() { None } else { Some(1) };
+                return Err(Utf16Error::new(self.read, error_len));
+            }
+            None => char::REPLACEMENT_CHARACTER,
+        };
+
+        let len = c.len_utf8();
+        let Some(dst) = self.dst.get_mut(self.written..self.written + len) else {
+            // an unpaired surrogate after the end of `dst` is still an error
+            if STRICT && let Some(err) = self.first_error() {
+                return Err(err);
+            }
+            too_short();
+        };
+
+        c.encode_utf8(dst);
+        self.read += width;
+        self.written += len;
+        Ok(())
+    }
+
+    /// Returns the error for the first unpaired surrogate from the next code unit on.
+    #[cold]
+    fn first_error(&self) -> Option<Utf16Error> {
+        let mut read = self.read;
+
+        while let Some(&unit) = self.src.get(read) {
+            match (unit, self.src.get(read + 1)) {
+                (0xd800..=0xdbff, Some(0xdc00..=0xdfff)) => read += 2,
+                // a high surrogate at the end may be completed by more input
+                (0xd800..=0xdbff, None) => return Some(Utf16Error::new(read, None)),
+                (0xd800..=0xdfff, _) => return Some(Utf16Error::new(read, Some(1))),
+                _ => read += 1,
+            }
+        }
+
+        None
+    }
+}
+
+/// Encodes 8 chars below U+10000, i.e. code units that are not surrogates, returning the number of bytes written.
+///
+/// Every char gets a 4-byte lane with all the bytes it may be encoded with,
+/// computed from the bytes of the code units with vector shifts and masks.
+/// The 1 to 3 bytes of the 4 lanes of each vector are then packed with a
+/// byte shuffle, selected by the widths of the chars. The bytes after the
+/// returned length are unspecified.
+#[inline(always)]
+fn encode_bmp(block: &[u16; 8], dst: &mut [u8; 28]) -> usize {
+    // the low byte of each code unit, followed by the high byte
+    let units = Simd8x16::from_array(unsafe { core::mem::transmute::<[u16; 8], [u8; 16]>(block.map(u16::to_le)) });
+    let swapped = Simd8x16::from_array(SWAP).lookup_16(units);
+
+    // the low byte of a unit is nonzero from U+0080 on, the high byte from U+0800 on, both 0xFF then
+    let above = units.saturating_sub(Simd8x16::from_array(ABOVE)) | (swapped & Simd8x16::from_array(LOW));
+    let above = (above.shr::<4>() | (above & Simd8x16::splat(0x0f))).lookup_16(Simd8x16::from_array(NONZERO));
+    let widths = above.bitmask();
+
+    // per char the ASCII byte and the lead byte of 3 bytes, which are interleaved with the last byte and the byte before it
+    let first = (units & Simd8x16::from_array(LOW)) | (units.shr::<4>() & Simd8x16::from_array(HIGH_NIBBLE)) | Simd8x16::from_array(LEADS_3);
+    let second = (units & Simd8x16::from_array(LAST_BITS))
+        | (units & Simd8x16::from_array(HIGH_NIBBLE)).lookup_16(Simd8x16::from_array(SHL_2))
+        | (swapped.shr::<6>() & Simd8x16::from_array(HIGH_2_BITS))
+        // the byte before the last one is the lead byte of chars of 2 bytes
+        | (Simd8x16::from_array(LEADS_2) ^ (above & Simd8x16::from_array(LEAD_BIT)));
+    let (low, high) = first.interleave(second);
+
+    let mut written = 0;
+    for (half, lanes) in [low, high].into_iter().enumerate() {
+        let (indices, len) = PACK[(widths >> (8 * half)) as usize & 0xff];
+        let packed = Simd8x16::from_array(indices).lookup_16(lanes);
+
+        dst[written..written + 16].copy_from_slice(&packed.to_array());
+        written += len as usize;
+    }
+
+    written
+}
+
+/// Shuffle indices that swap the bytes of each code unit.
+const SWAP: [u8; 16] = [1, 0, 3, 2, 5, 4, 7, 6, 9, 8, 11, 10, 13, 12, 15, 14];
+
+/// Subtrahends that leave a low byte nonzero from 0x80 on and a high byte from 0x08 on.
+const ABOVE: [u8; 16] = [0x7f, 0x07, 0x7f, 0x07, 0x7f, 0x07, 0x7f, 0x07, 0x7f, 0x07, 0x7f, 0x07, 0x7f, 0x07, 0x7f, 0x07];
+
+/// Lookup table for the nonzero nibbles.
+const NONZERO: [u8; 16] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+
+/// Mask of the low bytes of the code units.
+const LOW: [u8; 16] = [0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00, 0xff, 0x00];
+
+/// Mask of the low nibbles of the high bytes of the code units.
+const HIGH_NIBBLE: [u8; 16] = [0x00, 0x0f, 0x00, 0x0f, 0x00, 0x0f, 0x00, 0x0f, 0x00, 0x0f, 0x00, 0x0f, 0x00, 0x0f, 0x00, 0x0f];
+
+/// Mask of the 6 lowest bits of the low bytes of the code units.
+const LAST_BITS: [u8; 16] = [0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00];
+
+/// Mask of the 2 highest bits of the low bytes, shifted into the high bytes.
+const HIGH_2_BITS: [u8; 16] = [0x00, 0x03, 0x00, 0x03, 0x00, 0x03, 0x00, 0x03, 0x00, 0x03, 0x00, 0x03, 0x00, 0x03, 0x00, 0x03];
+
+/// The bit that turns the lead byte of 2 bytes into a continuation byte for chars of 3 bytes, in the high bytes.
+const LEAD_BIT: [u8; 16] = [0x00, 0x40, 0x00, 0x40, 0x00, 0x40, 0x00, 0x40, 0x00, 0x40, 0x00, 0x40, 0x00, 0x40, 0x00, 0x40];
+
+/// The prefix of the lead byte of 3 bytes, in the high bytes.
+const LEADS_3: [u8; 16] = [0x00, 0xe0, 0x00, 0xe0, 0x00, 0xe0, 0x00, 0xe0, 0x00, 0xe0, 0x00, 0xe0, 0x00, 0xe0, 0x00, 0xe0];
+
+/// The prefixes of the last byte and of the lead byte of 2 bytes.
+const LEADS_2: [u8; 16] = [0x80, 0xc0, 0x80, 0xc0, 0x80, 0xc0, 0x80, 0xc0, 0x80, 0xc0, 0x80, 0xc0, 0x80, 0xc0, 0x80, 0xc0];
+
+/// Lookup table for the bits 2 to 5 of the byte before the last one, from the low nibble of the high byte.
+const SHL_2: [u8; 16] = [0x00, 0x04, 0x08, 0x0c, 0x10, 0x14, 0x18, 0x1c, 0x20, 0x24, 0x28, 0x2c, 0x30, 0x34, 0x38, 0x3c];
+
+/// Shuffle indices that pack the bytes of 4 chars in 4-byte lanes, and the packed length.
+///
+/// A lane holds the ASCII byte, the last byte, the lead byte of 3 bytes and
+/// the byte before the last one. Indexed by 2 bits per char, which are set
+/// from U+0080 and from U+0800 on.
+static PACK: [([u8; 16], u8); 256] = pack_table();
+
+const fn pack_table() -> [([u8; 16], u8); 256] {
+    let mut table = [([0; 16], 0); 256];
+
+    let mut widths = 0;
+    while widths < 256 {
+        let mut len = 0;
+        let mut lane = 0;
+        while lane < 4 {
+            // the unused bits 0b10 are treated as 2 bytes
+            let bytes: &[u8] = match (widths >> (2 * lane)) & 3 {
+                0 => &[0],
+                3 => &[2, 3, 1],
+                _ => &[3, 1],
+            };
+
+            let mut i = 0;
+            while i < bytes.len() {
+                table[widths].0[len] = 4 * lane as u8 + bytes[i];
+                len += 1;
+                i += 1;
+            }
+            lane += 1;
+        }
+
+        table[widths].1 = len as u8;
+        widths += 1;
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixtures::TEXT;
+
+    /// The UTF-16 code units of `str`.
+    fn encode(str: &str, buf: &mut [u16; 1024]) -> usize {
+        let mut len = 0;
+        for unit in str.encode_utf16() {
+            buf[len] = unit;
+            len += 1;
+        }
+        len
+    }
+
+    #[test]
+    fn matches_encode_utf16() {
+        // ASCII chunks, chunks with multibyte chars and chars across chunk boundaries
+        let mut bytes = [b'a'; 512];
+        for chunk in bytes[200..].chunks_exact_mut(TEXT.len()) {
+            chunk.copy_from_slice(TEXT.as_bytes());
+        }
+        let text = crate::from_utf8(&bytes).unwrap();
+
+        for start in (0..80).filter(|&i| text.is_char_boundary(i)) {
+            for end in (start..text.len()).rev().take(70).filter(|&i| text.is_char_boundary(i)) {
+                let str = &text[start..end];
+
+                let mut expected = [0; 1024];
+                let len = encode(str, &mut expected);
+
+                let mut utf16 = [0; 1024];
+                assert_eq!(utf8_to_utf16(str.as_bytes(), &mut utf16), Ok(len));
+                assert_eq!(utf16[..len], expected[..len]);
+
+                // an exact-size buffer is enough
+                assert_eq!(utf8_to_utf16(str.as_bytes(), &mut utf16[..len]), Ok(len));
+            }
+        }
+    }
+
+    #[test]
+    fn bmp_chars() {
+        // the chars at the boundaries of each sequence length, in patterns that end at every position of a block
+        let chars = ['a', '\u{7f}', '\u{80}', 'Ő', 'ß', '\u{100}', '\u{7ff}', '\u{800}', '\u{1040}', '€', '世', '\u{d7ff}', '\u{e000}', '\u{ffff}'];
+
+        for stride in 1..chars.len() {
+            let mut bytes = [0; 512];
+            let mut len = 0;
+            for i in 0..200 {
+                len += chars[i * stride % chars.len()].encode_utf8(&mut bytes[len..]).len();
+            }
+            let text = crate::from_utf8(&bytes[..len]).unwrap();
+
+            for start in (0..20).filter(|&i| text.is_char_boundary(i)) {
+                let str = &text[start..];
+
+                let mut expected = [0; 1024];
+                let len = encode(str, &mut expected);
+
+                let mut utf16 = [0; 1024];
+                assert_eq!(utf8_to_utf16(str.as_bytes(), &mut utf16), Ok(len));
+                assert_eq!(utf16[..len], expected[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn errors_match_from_utf8() {
+        let mut bytes = [b'a'; 300];
+        bytes[100..100 + TEXT.len()].copy_from_slice(TEXT.as_bytes());
+
+        for position in (0..bytes.len()).step_by(3) {
+            for sequence in [&b"\x80"[..], b"\xC0\xAF", b"\xED\xA0\x80", b"\xF0\x9F\x98"] {
+                let mut invalid = bytes;
+                let end = (position + sequence.len()).min(invalid.len());
+                invalid[position..end].copy_from_slice(&sequence[..end - position]);
+
+                // replacing a continuation byte may keep the input valid
+                let expected = crate::from_utf8(&invalid).map(|str| str.encode_utf16().count());
+
+                let mut utf16 = [0; 300];
+                assert_eq!(utf8_to_utf16(&invalid, &mut utf16), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn error_before_short_buffer() {
+        // the error is reported although `dst` is full long before it
+        let mut bytes = [b'a'; 200];
+        bytes[150] = 0xFF;
+        assert_eq!(utf8_to_utf16(&bytes, &mut [0; 10]), Err(crate::from_utf8(&bytes).unwrap_err()));
+
+        // and after multibyte chars
+        bytes[100..100 + TEXT.len()].copy_from_slice(TEXT.as_bytes());
+        bytes[199] = 0xFF;
+        assert_eq!(utf8_to_utf16(&bytes, &mut [0; 110]), Err(crate::from_utf8(&bytes).unwrap_err()));
+
+        let mut utf16 = [0x4E16; 20];
+        utf16[15] = 0xDC00;
+        assert_eq!(utf16_to_utf8(&utf16, &mut [0; 8]), Err(Utf16Error::new(15, Some(1))));
+
+        // after a surrogate pair, and a high surrogate at the end
+        utf16[15] = 0x4E16;
+        utf16[10..12].copy_from_slice(&[0xD83E, 0xDD80]);
+        utf16[19] = 0xD800;
+        assert_eq!(utf16_to_utf8(&utf16, &mut [0; 8]), Err(Utf16Error::new(19, None)));
+    }
+
+    #[test]
+    #[should_panic(expected = "too short")]
+    fn short_buffer() {
+        let mut utf16 = [0; 63];
+        let _ = utf8_to_utf16(&[b'a'; 64], &mut utf16);
+    }
+
+    /// The UTF-8 encoding of `units`, with unpaired surrogates replaced.
+    fn decode(units: &[u16], buf: &mut [u8; 1024]) -> usize {
+        let mut len = 0;
+        for c in char::decode_utf16(units.iter().copied()) {
+            len += c.unwrap_or(char::REPLACEMENT_CHARACTER).encode_utf8(&mut buf[len..]).len();
+        }
+        len
+    }
+
+    #[test]
+    fn matches_decode_utf16() {
+        // ASCII blocks followed by multibyte chars and surrogate pairs
+        let mut units = [b'a' as u16; 200];
+        let mut text = [0; 1024];
+        let len = encode(TEXT, &mut text);
+        units[50..50 + len].copy_from_slice(&text[..len]);
+
+        // unpaired surrogates at every position, and a high surrogate that ends the input
+        for position in 0..units.len() {
+            for surrogate in [0xD800, 0xDBFF, 0xDC00, 0xDFFF] {
+                let mut invalid = units;
+                invalid[position] = surrogate;
+
+                for end in [units.len(), position + 1] {
+                    let invalid = &invalid[..end];
+
+                    let mut expected = [0; 1024];
+                    let len = decode(invalid, &mut expected);
+
+                    let mut utf8 = [0; 1024];
+                    assert_eq!(utf16_to_utf8_lossy(invalid, &mut utf8), len);
+                    assert_eq!(utf8[..len], expected[..len]);
+
+                    let valid_up_to = char::decode_utf16(invalid.iter().copied()).take_while(Result::is_ok).map(|c| c.unwrap().len_utf16()).sum::<usize>();
+                    match utf16_to_utf8(invalid, &mut utf8) {
+                        // replacing half of a pair may complete another one
+                        Ok(written) => assert_eq!((written, valid_up_to), (len, invalid.len())),
+                        Err(err) => {
+                            assert_eq!(err.valid_up_to(), valid_up_to);
+                            let truncated = valid_up_to + 1 == end && invalid[valid_up_to] < 0xDC00;
+                            assert_eq!(err.error_len(), if truncated { None } else { Some(1) });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn error_messages() {
+        use alloc::string::ToString;
+
+        let mut utf8 = [0; 8];
+        let err = utf16_to_utf8(&[0x61, 0xDC00, 0x62], &mut utf8).unwrap_err();
+        assert_eq!(err.to_string(), "unpaired surrogate at index 1");
+
+        let err = utf16_to_utf8(&[0x61, 0xD800], &mut utf8).unwrap_err();
+        assert_eq!(err.to_string(), "unpaired high surrogate at end of input at index 1");
+    }
+
+    #[test]
+    fn bmp_blocks() {
+        // chars of every width at every position of the blocks, ending in every position of a block
+        let chars = ['a', '\u{7F}', '\u{80}', 'é', '\u{7FF}', '\u{800}', '世', '\u{D7FF}', '\u{E000}', '\u{FFFF}'];
+
+        for seed in 0..chars.len() {
+            let mut units = [0; 200];
+            for (i, unit) in units.iter_mut().enumerate() {
+                *unit = chars[(i * i + 3 * i + seed) % chars.len()] as u16;
+            }
+
+            for end in 180..units.len() {
+                let mut expected = [0; 1024];
+                let len = decode(&units[..end], &mut expected);
+
+                // an exact-size buffer falls back to single chars at the end
+                let mut utf8 = [0; 1024];
+                assert_eq!(utf16_to_utf8(&units[..end], &mut utf8[..len]), Ok(len));
+                assert_eq!(utf8[..len], expected[..len]);
+                assert_eq!(utf16_to_utf8_lossy(&units[..end], &mut utf8), len);
+                assert_eq!(utf8[..len], expected[..len]);
+                assert_eq!(super::encode::<true, false>(&units[..end], &mut utf8), Ok(len));
+                assert_eq!(utf8[..len], expected[..len]);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too short")]
+    fn short_utf8_buffer() {
+        let mut utf8 = [0; 5];
+        let _ = utf16_to_utf8(&[0x4E16, 0x754C], &mut utf8);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vec() {
+        let text = TEXT.repeat(10);
+        assert_eq!(utf8_to_utf16_vec(text.as_bytes()).unwrap(), text.encode_utf16().collect::<Vec<_>>());
+        assert_eq!(utf8_to_utf16_vec(b"\xFF"), Err(crate::from_utf8(b"\xFF").unwrap_err()));
+    }
+}
@@ -0,0 +1,249 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use core::slice;
+
+use crate::transcode::{self, too_short};
+use crate::{Utf32Error, Utf8Error};
+
+/// Converts UTF-8 to UTF-32, validating it in the same pass.
+///
+/// Writes the scalar values of `src` to the start of `dst` and returns their
+/// number. Shares the decoding of [`utf8_to_utf16`](crate::utf8_to_utf16):
+/// the input is validated chunk by chunk with the widest SIMD instructions of
+/// the CPU, ASCII chunks are widened directly and chars of up to 3 bytes are
+/// decoded 16 bytes at a time with SIMD shuffles, whose UTF-16 code units are
+/// widened once more. The exact length that `dst` needs is returned by
+/// [`count_chars`](crate::count_chars), the length of `src` is always enough.
+///
+/// # Errors
+/// Fails on invalid UTF-8 with the same error as [`from_utf8`](crate::from_utf8).
+/// The contents of `dst` are unspecified then.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text. Invalid input is
+/// always reported as an error, even if `dst` is full before the error.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut utf32 = [0u32; 16];
+/// let len = utf8simd::utf8_to_utf32("Grüße, 🦀!".as_bytes(), &mut utf32).unwrap();
+/// assert_eq!(utf32[..len], ['G', 'r', 'ü', 'ß', 'e', ',', ' ', '🦀', '!'].map(u32::from));
+/// ```
+pub fn utf8_to_utf32(src: &[u8], dst: &mut [u32]) -> Result<usize, Utf8Error> {
+    transcode::decode_utf32(src, dst)
+}
+
+/// Converts UTF-8 to chars, validating it in the same pass.
+///
+/// See [`utf8_to_utf32`].
+///
+/// # Examples
+///
+/// ```rust
+/// let mut chars = ['\0'; 16];
+/// let len = utf8simd::utf8_to_chars("世界".as_bytes(), &mut chars).unwrap();
+/// assert_eq!(chars[..len], ['世', '界']);
+/// ```
+pub fn utf8_to_chars(src: &[u8], dst: &mut [char]) -> Result<usize, Utf8Error> {
+    // SAFETY: a char has the layout of a u32, and only scalar values are
+    // written, even if the input turns out to be invalid
+    let dst = unsafe { slice::from_raw_parts_mut(dst.as_mut_ptr().cast::<u32>(), dst.len()) };
+    transcode::decode_utf32(src, dst)
+}
+
+/// Converts UTF-8 to a vector of chars, validating it in the same pass.
+///
+/// See [`utf8_to_utf32`]. The vector is allocated with the length of `src`,
+/// which is enough for any valid input.
+///
+/// # Examples
+///
+/// ```rust
+/// let chars = utf8simd::utf8_to_chars_vec("Grüße, 世界!".as_bytes()).unwrap();
+/// assert_eq!(chars, "Grüße, 世界!".chars().collect::<Vec<_>>());
+/// ```
+#[cfg(feature = "alloc")]
+pub fn utf8_to_chars_vec(src: &[u8]) -> Result<Vec<char>, Utf8Error> {
+    let mut dst = alloc::vec!['\0'; src.len()];
+    let len = utf8_to_chars(src, &mut dst)?;
+    dst.truncate(len);
+    Ok(dst)
+}
+
+/// Converts UTF-32 to UTF-8, failing on code units that are not scalar values.
+///
+/// Writes the UTF-8 encoding of `src` to the start of `dst` and returns the
+/// number of bytes written. Blocks of ASCII code units are narrowed directly,
+/// all other code units are checked and encoded one by one. At most 4 bytes
+/// per code unit are needed.
+///
+/// # Errors
+/// Fails at the first surrogate or value above U+10FFFF, see [`Utf32Error`].
+/// `dst` holds the conversion of the code units before it then, as far as it
+/// fits.
+///
+/// # Panics
+/// Panics if `dst` is too short to hold the converted text. Invalid code units
+/// are always reported as an error, even if `dst` is full before them.
+///
+/// # Examples
+///
+/// ```rust
+/// let mut utf8 = [0u8; 16];
+/// let len = utf8simd::utf32_to_utf8(&[0x61, 0x1F980], &mut utf8).unwrap();
+/// assert_eq!(&utf8[..len], "a🦀".as_bytes());
+///
+/// let err = utf8simd::utf32_to_utf8(&[0x61, 0xD800], &mut utf8).unwrap_err();
+/// assert_eq!(err.valid_up_to(), 1);
+/// ```
+pub fn utf32_to_utf8(src: &[u32], dst: &mut [u8]) -> Result<usize, Utf32Error> {
+    let mut read = 0;
+    let mut written = 0;
+
+    while read < src.len() {
+        // blocks of ASCII are narrowed unit by unit, without encoding chars
+        if let (Some(block), Some(dst)) = (src.get(read..read + 16), dst.get_mut(written..written + 16))
+            && block.iter().fold(0, |any, &unit| any | unit) < 0x80
+        {
+            for (byte, &unit) in dst.iter_mut().zip(block) {
+                *byte = unit as u8;
+            }
+
+            read += 16;
+            written += 16;
+            continue;
+        }
+
+        let end = (read + 16).min(src.len());
+        for (i, &unit) in src[read..end].iter().enumerate() {
+            let Some(c) = char::from_u32(unit) else {
+                return Err(Utf32Error::new(read + i));
+            };
+
+            let Some(dst) = dst.get_mut(written..written + c.len_utf8()) else {
+                // an invalid code unit after the end of `dst` is still an error
+                return match src[read + i..].iter().position(|&unit| char::from_u32(unit).is_none()) {
+                    Some(position) => Err(Utf32Error::new(read + i + position)),
+                    None => too_short(),
+                };
+            };
+
+            written += c.encode_utf8(dst).len();
+        }
+
+        read = end;
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::fixtures::TEXT;
+
+    #[test]
+    fn matches_chars() {
+        // ASCII chunks, chunks with multibyte chars and chars across chunk boundaries
+        let mut bytes = [b'a'; 512];
+        for chunk in bytes[200..].chunks_exact_mut(TEXT.len()) {
+            chunk.copy_from_slice(TEXT.as_bytes());
+        }
+        let text = crate::from_utf8(&bytes).unwrap();
+
+        for start in (0..80).filter(|&i| text.is_char_boundary(i)) {
+            for end in (start..text.len()).rev().take(70).filter(|&i| text.is_char_boundary(i)) {
+                let str = &text[start..end];
+                let len = str.chars().count();
+
+                let mut utf32 = [0; 512];
+                assert_eq!(utf8_to_utf32(str.as_bytes(), &mut utf32[..len]), Ok(len));
+                assert!(utf32[..len].iter().copied().eq(str.chars().map(u32::from)));
+
+                let mut chars = ['\0'; 512];
+                assert_eq!(utf8_to_chars(str.as_bytes(), &mut chars), Ok(len));
+                assert!(chars[..len].iter().copied().eq(str.chars()));
+
+                // and back
+                let mut utf8 = [0; 512];
+                assert_eq!(utf32_to_utf8(&utf32[..len], &mut utf8), Ok(str.len()));
+                assert_eq!(&utf8[..str.len()], str.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let mut bytes = [b'a'; 200];
+        bytes[100..100 + TEXT.len()].copy_from_slice(TEXT.as_bytes());
+        let mut invalid = bytes;
+        invalid[150] = 0xFF;
+
+        let mut utf32 = [0; 200];
+        assert_eq!(utf8_to_utf32(&invalid, &mut utf32), Err(crate::from_utf8(&invalid).unwrap_err()));
+
+        let len = utf8_to_utf32(&bytes, &mut utf32).unwrap();
+        for position in 0..len {
+            for value in [0xD800, 0xDFFF, 0x110000, u32::MAX] {
+                let mut invalid = utf32;
+                invalid[position] = value;
+
+                let mut utf8 = [0; 800];
+                assert_eq!(utf32_to_utf8(&invalid[..len], &mut utf8), Err(Utf32Error::new(position)));
+            }
+        }
+    }
+
+    #[test]
+    fn bmp_chars() {
+        // the chars at the boundaries of each sequence length, in patterns that end at every position of a block
+        let chars = ['a', '\u{7f}', '\u{80}', 'Ő', 'ß', '\u{100}', '\u{7ff}', '\u{800}', '\u{1040}', '€', '世', '\u{d7ff}', '\u{e000}', '\u{ffff}'];
+
+        for stride in 1..chars.len() {
+            let mut bytes = [0; 512];
+            let mut len = 0;
+            for i in 0..200 {
+                len += chars[i * stride % chars.len()].encode_utf8(&mut bytes[len..]).len();
+            }
+            let text = crate::from_utf8(&bytes[..len]).unwrap();
+
+            for start in (0..20).filter(|&i| text.is_char_boundary(i)) {
+                let str = &text[start..];
+
+                let mut chars = ['\0'; 512];
+                assert_eq!(utf8_to_chars(str.as_bytes(), &mut chars), Ok(str.chars().count()));
+                assert!(chars.iter().copied().zip(str.chars()).all(|(c, expected)| c == expected));
+            }
+        }
+    }
+
+    #[test]
+    fn error_before_short_buffer() {
+        // the errors are reported although `dst` is full long before them
+        let mut bytes = [b'a'; 200];
+        bytes[150] = 0xFF;
+        assert_eq!(utf8_to_utf32(&bytes, &mut [0; 10]), Err(crate::from_utf8(&bytes).unwrap_err()));
+
+        let mut utf32 = [0x1F980; 20];
+        utf32[15] = 0xD800;
+        assert_eq!(utf32_to_utf8(&utf32, &mut [0; 8]), Err(Utf32Error::new(15)));
+    }
+
+    #[test]
+    #[should_panic(expected = "too short")]
+    fn short_buffer() {
+        let mut utf8 = [0; 7];
+        let _ = utf32_to_utf8(&[0x1F980, 0x1F980], &mut utf8);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn vec() {
+        let text = TEXT.repeat(10);
+        assert_eq!(utf8_to_chars_vec(text.as_bytes()).unwrap(), text.chars().collect::<Vec<_>>());
+        assert_eq!(utf8_to_chars_vec(b"\xFF"), Err(crate::from_utf8(b"\xFF").unwrap_err()));
+    }
+}
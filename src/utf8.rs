@@ -1,17 +1,142 @@
-use core::{mem, slice};
-use core::simd::Simd;
+use core::mem;
 
-use crate::{Utf8Error, Utf8Validator};
+use crate::Utf8Error;
+use crate::validator::{COUNT_CHARS, COUNT_UTF16};
+#[cfg(any(test, not(feature = "swar")))]
+use crate::{simd::Vector, validator::{Counts, COUNT_NONE}, Utf8Validator};
+#[cfg(any(test, not(feature = "swar")))]
+use core::slice;
 
 /// Converts a slice of bytes to a string slice.
 pub fn from_utf8(v: &[u8]) -> Result<&str, Utf8Error> {
     // not worth it to use SIMD
     if v.len() < 128 {
-        return core::str::from_utf8(v).map_err(|_| Utf8Error);
+        return core::str::from_utf8(v).map_err(|err| Utf8Error::from_core(err, v));
     }
 
+    validate(v)?;
+
+    Ok(unsafe { from_utf8_unchecked(v) })
+}
+
+/// Converts a slice of bytes to a string slice, along with its number of chars.
+///
+/// The chars are counted in the same pass as the validation, which is faster
+/// than calling `chars().count()` on the result of [`from_utf8`].
+///
+/// # Examples
+///
+/// ```rust
+/// let (str, len) = utf8simd::from_utf8_with_len("Grüße, 世界!".as_bytes()).unwrap();
+/// assert_eq!(str, "Grüße, 世界!");
+/// assert_eq!(len, 10);
+/// ```
+pub fn from_utf8_with_len(v: &[u8]) -> Result<(&str, usize), Utf8Error> {
+    let len = count_chars(v)?;
+    Ok((unsafe { from_utf8_unchecked(v) }, len))
+}
+
+/// Counts the chars (Unicode scalar values) of a slice of bytes, validating it in the same pass.
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(utf8simd::count_chars("🦀 and 世界".as_bytes()), Ok(8));
+/// assert!(utf8simd::count_chars(b"\xF0\x9F\xA6").is_err());
+/// ```
+pub fn count_chars(v: &[u8]) -> Result<usize, Utf8Error> {
+    count::<COUNT_CHARS>(v)
+}
+
+/// Computes the length in UTF-16 code units of a slice of bytes, validating it in the same pass.
+///
+/// The length is the number of chars plus the number of 4-byte sequences,
+/// whose chars are encoded as surrogate pairs in UTF-16. It is the exact size
+/// of a buffer that holds the slice converted to UTF-16.
+///
+/// # Examples
+///
+/// ```rust
+/// let text = "🦀 and 世界";
+/// assert_eq!(utf8simd::utf16_len(text.as_bytes()), Ok(text.encode_utf16().count()));
+/// assert_eq!(utf8simd::utf16_len(text.as_bytes()), Ok(9));
+/// ```
+pub fn utf16_len(v: &[u8]) -> Result<usize, Utf8Error> {
+    count::<COUNT_UTF16>(v)
+}
+
+/// Validates a slice and counts what `COUNT` selects, one of [`COUNT_CHARS`] and [`COUNT_UTF16`].
+#[inline]
+fn count<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    // not worth it to use SIMD
+    if v.len() < 128 {
+        return count_scalar::<COUNT>(v);
+    }
+
+    #[cfg(feature = "swar")]
+    return crate::swar::count::<COUNT>(v);
+
+    #[cfg(utf8simd_dispatch)]
+    return crate::dispatch::count::<COUNT>(v);
+
+    #[cfg(not(any(feature = "swar", utf8simd_dispatch)))]
+    count_simd::<crate::simd::Native, COUNT>(v)
+}
+
+/// Validates a slice with the standard library and counts what `COUNT` selects.
+pub(crate) fn count_scalar<const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    let str = core::str::from_utf8(v).map_err(|err| Utf8Error::from_core(err, v))?;
+
+    Ok(match COUNT {
+        COUNT_UTF16 => str.chars().map(char::len_utf16).sum(),
+        _ => str.chars().count(),
+    })
+}
+
+/// Validates a slice with the best SIMD kernel for the CPU, or word by word with the `swar` feature.
+#[inline]
+fn validate(v: &[u8]) -> Result<(), Utf8Error> {
+    #[cfg(feature = "swar")]
+    return crate::swar::validate(v);
+
+    #[cfg(utf8simd_dispatch)]
+    return crate::dispatch::validate(v);
+
+    #[cfg(not(any(feature = "swar", utf8simd_dispatch)))]
+    validate_simd::<crate::simd::Native>(v)
+}
+
+/// Validates a slice with SIMD instructions, using vectors of type `V`.
+///
+/// Always inlined, see [`Vector`].
+#[cfg(any(test, not(feature = "swar")))]
+#[inline(always)]
+pub(crate) fn validate_simd<V: Vector>(v: &[u8]) -> Result<(), Utf8Error> {
+    validate_chunks::<V, COUNT_NONE>(v)?;
+    Ok(())
+}
+
+/// Validates a slice with SIMD instructions and counts what `COUNT` selects, see [`validate_simd`].
+#[cfg(any(test, not(feature = "swar")))]
+#[inline(always)]
+pub(crate) fn count_simd<V: Vector, const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
+    validate_chunks::<V, COUNT>(v)
+}
+
+/// Validates a slice in 64-byte chunks, returning the count selected by `COUNT`, or 0 for [`COUNT_NONE`].
+#[cfg(any(test, not(feature = "swar")))]
+#[inline(always)]
+fn validate_chunks<V: Vector, const COUNT: u8>(v: &[u8]) -> Result<usize, Utf8Error> {
     let mut validator = Utf8Validator::new();
 
+    // counted bytes, the zero padding of partial chunks contains none of them
+    let mut continuations = 0;
+    let mut four_byte_leads = 0;
+    let mut add = |counts: Counts| {
+        continuations += counts.continuations as usize;
+        four_byte_leads += counts.four_byte_leads as usize;
+    };
+
     // data and length
     let mut ptr = v.as_ptr();
     let len = v.len();
@@ -19,6 +144,9 @@ pub fn from_utf8(v: &[u8]) -> Result<&str, Utf8Error> {
     // end of the slice
     let end = unsafe { ptr.add(len) };
 
+    // exact error location, computed once the SIMD pass flags a chunk
+    let error_at = |ptr: *const u8| locate_error(v, unsafe { ptr.offset_from_unsigned(v.as_ptr()) });
+
     // alignment offset for 64-byte boundary
     let offset = ptr.align_offset(64);
 
@@ -26,15 +154,14 @@ pub fn from_utf8(v: &[u8]) -> Result<&str, Utf8Error> {
     if offset < len {
         let mut padded = [0u8; 64];
         padded[64 - offset..].copy_from_slice(&v[..offset]);
-        let chunk = Simd::from_array(padded);
-        validator.next(&chunk)?;
+        add(validator.process::<V, COUNT>(&padded, 64).map_err(|_| error_at(ptr))?);
         ptr = unsafe { ptr.add(offset) };
     }
 
     // process aligned 64-byte chunks
     while unsafe { ptr.add(64) } <= end {
         let chunk = unsafe { &*(ptr as *const _) };
-        validator.next(chunk)?;
+        add(validator.process::<V, COUNT>(chunk, 64).map_err(|_| error_at(ptr))?);
         ptr = unsafe { ptr.add(64) };
     }
 
@@ -43,13 +170,54 @@ pub fn from_utf8(v: &[u8]) -> Result<&str, Utf8Error> {
     let remaining = unsafe { slice::from_raw_parts(ptr, len) };
     let mut padded = [0u8; 64];
     padded[..len].copy_from_slice(remaining);
-    let chunk = Simd::from_array(padded);
-    validator.next(&chunk)?;
+    add(validator.process::<V, COUNT>(&padded, 64).map_err(|_| error_at(ptr))?);
 
     // check for incomplete bytes
-    validator.finish()?;
+    validator.finish().map_err(|_| error_at(ptr))?;
 
-    Ok(unsafe { from_utf8_unchecked(v) })
+    Ok(match COUNT {
+        COUNT_CHARS => v.len() - continuations,
+        COUNT_UTF16 => v.len() - continuations + four_byte_leads,
+        _ => 0,
+    })
+}
+
+/// Converts a mutable slice of bytes to a mutable string slice.
+pub fn from_utf8_mut(v: &mut [u8]) -> Result<&mut str, Utf8Error> {
+    from_utf8(v)?;
+    Ok(unsafe { from_utf8_unchecked_mut(v) })
+}
+
+/// Validates `v` with scalar code, starting from the sequence that contains `from`.
+///
+/// All bytes before `from` must be known to be valid UTF-8, except for an
+/// incomplete sequence of up to 3 bytes right before `from`.
+pub(crate) fn validate_from(v: &[u8], from: usize) -> Result<(), Utf8Error> {
+    // step back to the lead byte of a sequence that may continue at `from`
+    let mut start = from.saturating_sub(3);
+    while start < from && is_continuation(v[start]) {
+        start += 1;
+    }
+
+    match core::str::from_utf8(&v[start..]) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Utf8Error::from_core(err, &v[start..]).rebase(start)),
+    }
+}
+
+/// Locates the first invalid sequence in `v`, see [`validate_from`].
+#[cold]
+pub(crate) fn locate_error(v: &[u8], from: usize) -> Utf8Error {
+    match validate_from(v, from) {
+        Err(err) => err,
+        Ok(()) => unreachable!("SIMD and scalar validation disagree"),
+    }
+}
+
+/// Checks whether a byte is a UTF-8 continuation byte (`10______`).
+#[inline]
+pub(crate) const fn is_continuation(byte: u8) -> bool {
+    (byte as i8) < -0x40
 }
 
 /// Converts a slice of bytes to a string slice without checking that the string contains valid UTF-8.
@@ -61,9 +229,18 @@ pub const unsafe fn from_utf8_unchecked(v: &[u8]) -> &str {
     unsafe { mem::transmute(v) }
 }
 
+/// Converts a mutable slice of bytes to a mutable string slice without checking that the string contains valid UTF-8.
+///
+/// # Safety
+/// The bytes passed in must be valid UTF-8.
+pub const unsafe fn from_utf8_unchecked_mut(v: &mut [u8]) -> &mut str {
+    unsafe { &mut *(v as *mut [u8] as *mut str) }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::Utf8ErrorKind;
 
     #[test]
     fn valid_utf8() {
@@ -79,10 +256,142 @@ mod tests {
         assert_eq!(bytes, str.as_bytes());
     }
 
+    #[test]
+    fn valid_utf8_mut() {
+        let mut bytes = *b"hello, world!";
+        let str = from_utf8_mut(&mut bytes).unwrap();
+        str.make_ascii_uppercase();
+        assert_eq!(&bytes, b"HELLO, WORLD!");
+    }
+
     #[test]
     fn invalid_utf8() {
         let bytes = b"\x1F\x8Babcdefg";
         let err = from_utf8(bytes).unwrap_err();
-        assert_eq!(err, Utf8Error);
+        assert_eq!(err.valid_up_to(), 1);
+        assert_eq!(err.error_len(), Some(1));
+        assert_eq!(err.kind(), Utf8ErrorKind::UnexpectedContinuation);
+    }
+
+    #[test]
+    fn error_kind() {
+        let sequences: [(&[u8], Utf8ErrorKind); 15] = [
+            (b"\x80", Utf8ErrorKind::UnexpectedContinuation),
+            (b"\xC3\xA9\xA9", Utf8ErrorKind::UnexpectedContinuation),
+            (b"\xC3\x28", Utf8ErrorKind::TooShort),
+            (b"\xE2\x82\x28", Utf8ErrorKind::TooShort),
+            (b"\xC0\xAF", Utf8ErrorKind::Overlong),
+            (b"\xE0\x80\xAF", Utf8ErrorKind::Overlong),
+            (b"\xF0\x80\x80\xAF", Utf8ErrorKind::Overlong),
+            (b"\xED\xA0\x80", Utf8ErrorKind::Surrogate),
+            (b"\xF4\x90\x80\x80", Utf8ErrorKind::TooLarge),
+            (b"\xF5\x80\x80\x80", Utf8ErrorKind::TooLarge),
+            (b"\xF8\x88\x80\x80\x80", Utf8ErrorKind::InvalidByte),
+            (b"\xFE", Utf8ErrorKind::InvalidByte),
+            (b"\xFF", Utf8ErrorKind::InvalidByte),
+            (b"\xE2\x82", Utf8ErrorKind::Truncated),
+            (b"\xF0\x9F\x98", Utf8ErrorKind::Truncated),
+        ];
+
+        for (sequence, kind) in sequences {
+            // scalar path for short input
+            assert_eq!(from_utf8(sequence).unwrap_err().kind(), kind);
+
+            // SIMD path, with the sequence at the end of the input
+            let mut buf = [b'a'; 200];
+            buf[200 - sequence.len()..].copy_from_slice(sequence);
+            assert_eq!(from_utf8(&buf).unwrap_err().kind(), kind);
+        }
+    }
+
+    #[test]
+    fn error_position() {
+        #[repr(align(64))]
+        struct Aligned([u8; 320]);
+
+        let sequences: [&[u8]; 8] = [
+            b"\x80",
+            b"\xC3\x28",
+            b"\xC0\xAF",
+            b"\xE2\x82",
+            b"\xE2\x82\xAC\xAC",
+            b"\xED\xA0\x80",
+            b"\xF0\x9F\x98",
+            b"\xF4\x90\x80\x80",
+        ];
+
+        // move the invalid sequence through the unaligned prefix, the aligned chunks and the remainder
+        for sequence in sequences {
+            for position in 0..320 - sequence.len() {
+                let mut buf = Aligned([b'a'; 320]);
+                buf.0[position..position + sequence.len()].copy_from_slice(sequence);
+
+                for start in [0, 1, 5] {
+                    if start > position {
+                        continue;
+                    }
+
+                    for v in [&buf.0[start..], &buf.0[start..position + sequence.len()]] {
+                        let expected = core::str::from_utf8(v).unwrap_err();
+                        let err = from_utf8(v).unwrap_err();
+                        assert_eq!(err.valid_up_to(), expected.valid_up_to());
+                        assert_eq!(err.error_len(), expected.error_len());
+
+                        // the SIMD kernel on its own, which is not used by `from_utf8` with the `swar` feature
+                        assert_eq!(validate_simd::<crate::simd::Simd8x16>(v), Err(err));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn char_and_utf16_count() {
+        use crate::fixtures::TEXT;
+
+        let mut bytes = [0u8; 4 * TEXT.len()];
+        for chunk in bytes.chunks_mut(TEXT.len()) {
+            chunk.copy_from_slice(TEXT.as_bytes());
+        }
+        let text = from_utf8(&bytes).unwrap();
+
+        // through the unaligned prefix, the aligned chunks and the remainder
+        for start in (0..100).filter(|&i| text.is_char_boundary(i)) {
+            for end in [text.len(), text.len() - 1, text.len() - 30] {
+                let Some(str) = text.get(start..end) else { continue };
+                let expected = str.chars().count();
+
+                assert_eq!(count_chars(str.as_bytes()), Ok(expected));
+                assert_eq!(count_simd::<crate::simd::Simd8x16, COUNT_CHARS>(str.as_bytes()), Ok(expected));
+                assert_eq!(from_utf8_with_len(str.as_bytes()), Ok((str, expected)));
+
+                let expected = str.encode_utf16().count();
+                assert_eq!(utf16_len(str.as_bytes()), Ok(expected));
+                assert_eq!(count_simd::<crate::simd::Simd8x16, COUNT_UTF16>(str.as_bytes()), Ok(expected));
+            }
+        }
+
+        let mut invalid = bytes;
+        invalid[150] = 0x80;
+        assert_eq!(count_chars(&invalid), Err(from_utf8(&invalid).unwrap_err()));
+        assert_eq!(count_chars(b"\xE2\x82"), Err(from_utf8(b"\xE2\x82").unwrap_err()));
+        assert_eq!(utf16_len(&invalid), Err(from_utf8(&invalid).unwrap_err()));
+    }
+
+    #[test]
+    fn incomplete_before_ascii_chunk() {
+        #[repr(align(64))]
+        struct Aligned([u8; 192]);
+
+        // a truncated sequence at the end of a chunk, followed by an ASCII chunk and
+        // the continuation bytes it was missing
+        let mut buf = Aligned([b'a'; 192]);
+        buf.0[63] = 0xE2;
+        buf.0[128] = 0x82;
+        buf.0[129] = 0xAC;
+
+        let err = from_utf8(&buf.0).unwrap_err();
+        assert_eq!(err.valid_up_to(), 63);
+        assert_eq!(err.error_len(), Some(1));
     }
 }
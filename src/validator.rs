@@ -1,8 +1,25 @@
-use core::simd::num::SimdUint;
-use core::simd::Simd;
-
 use crate::error::Utf8Error;
-use crate::simd::Simd8x16;
+use crate::simd::{Simd8x16, Vector};
+use crate::utf8;
+
+// what `Utf8Validator::process` counts besides validating
+/// Only validate.
+pub(crate) const COUNT_NONE: u8 = 0;
+/// Count the bytes needed for the number of chars.
+pub(crate) const COUNT_CHARS: u8 = 1;
+/// Count the bytes needed for the number of UTF-16 code units.
+pub(crate) const COUNT_UTF16: u8 = 2;
+
+/// Bytes of a chunk counted by [`Utf8Validator::process`], zero if not requested.
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) struct Counts {
+    /// Continuation bytes, which do not start a char
+    pub continuations: u32,
+    /// Lead bytes of 4-byte sequences, whose chars take two UTF-16 code units
+    pub four_byte_leads: u32,
+    /// Whether the chunk is ASCII, which its fast path has checked anyway
+    pub ascii: bool,
+}
 
 /// A stateful UTF-8 validator that processes data in 64-byte chunks.
 ///
@@ -10,23 +27,26 @@ use crate::simd::Simd8x16;
 /// sequences that may span chunk boundaries. It uses SIMD operations to
 /// achieve high performance by processing multiple bytes simultaneously.
 ///
+/// Data can be passed either as 64-byte chunks with [`next`](Self::next), or
+/// as slices of any size with [`update`](Self::update), which buffers partial
+/// chunks between calls.
+///
 /// # Examples
 ///
 /// ```rust
-/// # #![feature(portable_simd)]
 /// # use utf8simd::Utf8Validator;
-/// # use core::simd::Simd;
 /// let mut validator = Utf8Validator::new();
 ///
-/// // process some UTF-8 data
+/// // process some UTF-8 data, padded to 64 bytes
 /// let data = "Hello, world! 🦀".as_bytes();
-/// let chunk = Simd::load_or_default(data);
+/// let mut chunk = [0u8; 64];
+/// chunk[..data.len()].copy_from_slice(data);
 /// validator.next(&chunk).unwrap();
 ///
 /// // finish validation
 /// validator.finish().unwrap();
 /// ```
-#[derive(Debug, Default)]
+#[derive(Clone, Debug)]
 pub struct Utf8Validator {
     /// Accumulated error state across processed chunks
     error: Simd8x16,
@@ -34,6 +54,37 @@ pub struct Utf8Validator {
     previous: Simd8x16,
     /// Incomplete multibyte sequences at the end of the previous chunk
     incomplete: Simd8x16,
+    /// Number of bytes processed before the current chunk
+    processed: usize,
+    /// Location of the first error, once one has been detected
+    failure: Option<Utf8Error>,
+    /// Partial chunk carried over between calls to `update`
+    buffer: [u8; 64],
+    /// Number of bytes in the partial chunk
+    buffered: usize,
+}
+
+/// The state of a [`Utf8Validator`] before [`stage`](Utf8Validator::stage), to which [`commit`](Utf8Validator::commit) may return.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Checkpoint {
+    previous: Simd8x16,
+    incomplete: Simd8x16,
+    processed: usize,
+}
+
+impl Default for Utf8Validator {
+    fn default() -> Self {
+        Self {
+            error: Simd8x16::default(),
+            previous: Simd8x16::default(),
+            incomplete: Simd8x16::default(),
+            processed: 0,
+            failure: None,
+            buffer: [0; 64],
+            buffered: 0,
+        }
+    }
 }
 
 impl Utf8Validator {
@@ -54,32 +105,289 @@ impl Utf8Validator {
     /// This method processes exactly 64 bytes of input data using SIMD operations.
     /// It includes an ASCII fast-path optimization that quickly validates pure ASCII.
     ///
+    /// Error positions are reported relative to the first byte passed to the
+    /// validator, counting 64 bytes per processed chunk.
+    ///
+    /// If [`update`](Self::update) has buffered a partial chunk, the data is
+    /// passed to `update` instead, so that all bytes are validated in the
+    /// order they were passed.
+    ///
     /// # Examples
     ///
     /// ```rust
-    /// # #![feature(portable_simd)]
     /// # use utf8simd::Utf8Validator;
-    /// # use core::simd::Simd;
     /// let mut validator = Utf8Validator::new();
-    /// let data = "A".repeat(64);
-    /// let chunk = Simd::from_slice(data.as_bytes());
+    /// let chunk = [b'A'; 64];
     /// validator.next(&chunk).unwrap();
     /// ```
     #[inline]
-    pub fn next(&mut self, data: &Simd<u8, 64>) -> crate::Result<()> {
-        // fast path for ASCII-only data
-        if core::intrinsics::likely(is_ascii(data)) {
+    pub fn next(&mut self, data: &[u8; 64]) -> crate::Result<()> {
+        if self.buffered > 0 {
+            return self.update(data);
+        }
+
+        self.process_slice(data)
+    }
+
+    /// Validates a slice of data of any length.
+    ///
+    /// Full 64-byte chunks are validated right away, while the remaining bytes
+    /// are buffered until the next call to `update` or [`finish`](Self::finish).
+    /// Errors may therefore be reported up to 63 bytes late. Error positions are
+    /// reported relative to the first byte passed to the validator.
+    ///
+    /// It may be mixed with [`next`](Self::next) on the same validator: while
+    /// a partial chunk is buffered, `next` passes its chunk on to `update`, so
+    /// the bytes are validated in the order they were passed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use utf8simd::Utf8Validator;
+    /// let mut validator = Utf8Validator::new();
+    ///
+    /// // a multibyte sequence split across two reads
+    /// validator.update("Hello, 世".as_bytes()).unwrap();
+    /// validator.update(&"界!".as_bytes()[..2]).unwrap();
+    /// validator.update(&"界!".as_bytes()[2..]).unwrap();
+    ///
+    /// validator.finish().unwrap();
+    /// ```
+    pub fn update(&mut self, mut bytes: &[u8]) -> crate::Result<()> {
+        if let Some(err) = self.failure {
+            return Err(err);
+        }
+
+        // complete a partial chunk from a previous call first
+        if self.buffered > 0 {
+            let len = bytes.len().min(64 - self.buffered);
+            self.buffer[self.buffered..self.buffered + len].copy_from_slice(&bytes[..len]);
+            self.buffered += len;
+            bytes = &bytes[len..];
+
+            if self.buffered < 64 {
+                return Ok(());
+            }
+
+            self.buffered = 0;
+            let chunk = self.buffer;
+            self.process_slice(&chunk)?;
+        }
+
+        let (chunks, remainder) = bytes.split_at(bytes.len() / 64 * 64);
+        self.process_slice(chunks)?;
+
+        self.buffer[..remainder.len()].copy_from_slice(remainder);
+        self.buffered = remainder.len();
+
+        Ok(())
+    }
+
+    /// Checks all data passed so far, tolerating an incomplete sequence at the end.
+    ///
+    /// Returns the number of trailing bytes (0 to 3) that form an incomplete,
+    /// but so far valid, multibyte sequence. All bytes before them are valid
+    /// UTF-8, including bytes still buffered by [`update`](Self::update). Unlike
+    /// [`finish`](Self::finish), validation can continue with more data
+    /// afterwards.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use utf8simd::Utf8Validator;
+    /// let mut validator = Utf8Validator::new();
+    ///
+    /// // a frame that ends with the first byte of '世'
+    /// let frame = &"Grüße, 世界".as_bytes()[..10];
+    /// validator.update(frame).unwrap();
+    ///
+    /// let incomplete = validator.check_partial().unwrap();
+    /// assert_eq!(incomplete, 1);
+    ///
+    /// // SAFETY: all bytes before the incomplete sequence are valid UTF-8
+    /// let str = unsafe { utf8simd::from_utf8_unchecked(&frame[..frame.len() - incomplete]) };
+    /// assert_eq!(str, "Grüße, ");
+    /// ```
+    pub fn check_partial(&mut self) -> crate::Result<usize> {
+        if let Some(err) = self.failure {
+            return Err(err);
+        }
+
+        // only the end of the last chunk and the buffered bytes are left to check
+        self.check_pending(&self.buffer[..self.buffered]).inspect_err(|&err| self.failure = Some(err))
+    }
+
+    /// Checks the end of the last chunk and the `pending` bytes after it, see [`check_partial`](Self::check_partial).
+    fn check_pending(&self, pending: &[u8]) -> crate::Result<usize> {
+        let (window, tail) = window(self.previous, pending, self.processed);
+        let window = &window[..tail + pending.len()];
+
+        match utf8::validate_from(window, tail) {
+            Ok(()) => Ok(0),
+            Err(err) if err.error_len().is_none() => Ok(window.len() - err.valid_up_to()),
+            Err(err) => Err(err.rebase(self.processed - tail)),
+        }
+    }
+
+    /// Validates `bytes` like [`update`](Self::update) followed by
+    /// [`check_partial`](Self::check_partial), without buffering them yet.
+    ///
+    /// The chunks completed by `bytes` are processed, but the partial chunk is
+    /// left as it was, so that [`commit`](Self::commit) can still take back
+    /// the bytes that were not accepted by the receiver. An error is final.
+    #[cfg(feature = "std")]
+    pub(crate) fn stage(&mut self, bytes: &[u8]) -> crate::Result<Checkpoint> {
+        if let Some(err) = self.failure {
+            return Err(err);
+        }
+
+        let checkpoint = Checkpoint { previous: self.previous, incomplete: self.incomplete, processed: self.processed };
+
+        let mut pending = [0u8; 64];
+        let pending = if self.buffered + bytes.len() < 64 {
+            pending[..self.buffered].copy_from_slice(&self.buffer[..self.buffered]);
+            pending[self.buffered..self.buffered + bytes.len()].copy_from_slice(bytes);
+            &pending[..self.buffered + bytes.len()]
+        } else {
+            // complete the partial chunk in a copy
+            let (first, rest) = bytes.split_at(64 - self.buffered);
+            pending[..self.buffered].copy_from_slice(&self.buffer[..self.buffered]);
+            pending[self.buffered..].copy_from_slice(first);
+            self.process_slice(&pending)?;
+
+            let (chunks, remainder) = rest.split_at(rest.len() / 64 * 64);
+            self.process_slice(chunks)?;
+            remainder
+        };
+
+        self.check_pending(pending).inspect_err(|&err| self.failure = Some(err))?;
+        Ok(checkpoint)
+    }
+
+    /// Advances the validator by the first `accepted` bytes of `bytes`, which were checked by [`stage`](Self::stage).
+    #[cfg(feature = "std")]
+    pub(crate) fn commit(&mut self, checkpoint: Checkpoint, bytes: &[u8], accepted: usize) -> crate::Result<()> {
+        if accepted == bytes.len() {
+            // the completed chunks have been processed, buffer the rest
+            if self.buffered + bytes.len() < 64 {
+                self.buffer[self.buffered..self.buffered + bytes.len()].copy_from_slice(bytes);
+                self.buffered += bytes.len();
+            } else {
+                let len = (bytes.len() - (64 - self.buffered)) % 64;
+                self.buffer[..len].copy_from_slice(&bytes[bytes.len() - len..]);
+                self.buffered = len;
+            }
+
             return Ok(());
         }
 
-        self.validate_utf8(data)
+        // a prefix of checked bytes cannot be invalid, at most end in an incomplete sequence
+        self.previous = checkpoint.previous;
+        self.incomplete = checkpoint.incomplete;
+        self.processed = checkpoint.processed;
+        self.update(&bytes[..accepted])
+    }
+
+    /// Returns the number of bytes passed to the validator, including the buffered ones.
+    #[cfg(feature = "std")]
+    pub(crate) fn position(&self) -> usize {
+        self.processed + self.buffered
+    }
+
+    /// Validates consecutive 64-byte chunks with the best kernel for the CPU.
+    ///
+    /// A shorter last chunk is padded with zeros, which is only valid at the
+    /// end of the input.
+    #[inline]
+    fn process_slice(&mut self, bytes: &[u8]) -> crate::Result<()> {
+        #[cfg(utf8simd_dispatch)]
+        return crate::dispatch::process(self, bytes);
+
+        #[cfg(not(utf8simd_dispatch))]
+        self.process_simd::<crate::simd::Native>(bytes)
+    }
+
+    /// Validates consecutive chunks with vectors of type `V`, see [`process_slice`](Self::process_slice).
+    ///
+    /// Always inlined, see [`Vector`].
+    #[inline(always)]
+    pub(crate) fn process_simd<V: Vector>(&mut self, bytes: &[u8]) -> crate::Result<()> {
+        let mut chunks = bytes.chunks_exact(64);
+        for chunk in &mut chunks {
+            self.process::<V, COUNT_NONE>(chunk.try_into().unwrap(), 64)?;
+        }
+
+        let remainder = chunks.remainder();
+        if !remainder.is_empty() {
+            let mut padded = [0u8; 64];
+            padded[..remainder.len()].copy_from_slice(remainder);
+            self.process::<V, COUNT_NONE>(&padded, remainder.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates consecutive chunks with scalar code, for CPUs without the byte shuffles of SSSE3.
+    ///
+    /// Leaves the same state behind as [`process_simd`](Self::process_simd).
+    #[cfg(utf8simd_dispatch)]
+    pub(crate) fn process_scalar(&mut self, bytes: &[u8]) -> crate::Result<()> {
+        for data in bytes.chunks(64) {
+            let previous = self.previous;
+
+            // an incomplete sequence at the end may still be completed by the next chunk,
+            // unless it is followed by the zero padding of a partial chunk
+            let (window, tail) = window(previous, data, self.processed);
+            if let Err(err) = utf8::validate_from(&window[..tail + 64], tail)
+                && err.error_len().is_some()
+            {
+                self.error = Simd8x16::from_array([0xff; 16]);
+            }
+
+            let mut chunk = [0u8; 64];
+            chunk[..data.len()].copy_from_slice(data);
+            self.previous = Simd8x16::from_array(chunk[48..].try_into().unwrap());
+            self.incomplete = is_incomplete(self.previous);
+
+            let result = self.check_error(previous, data);
+            self.processed += data.len();
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates a 64-byte chunk holding `len` bytes of input, split into vectors of type `V`.
+    ///
+    /// Also counts the bytes selected by `COUNT`, one of the `COUNT_*` constants,
+    /// and reports whether the chunk is ASCII.
+    #[inline(always)]
+    pub(crate) fn process<V: Vector, const COUNT: u8>(&mut self, data: &[u8; 64], len: usize) -> crate::Result<Counts> {
+        let previous = self.previous;
+        let mut counts = Counts::default();
+
+        // fast path for ASCII-only data
+        if likely(is_ascii::<V>(data)) {
+            // ASCII cannot continue an incomplete sequence of the previous chunk
+            self.error |= self.incomplete;
+            self.incomplete = Simd8x16::default();
+            self.previous = Simd8x16::default();
+            counts.ascii = true;
+        } else {
+            counts = self.validate_utf8::<V, COUNT>(data);
+        }
+
+        let result = self.check_error(previous, &data[..len]);
+        self.processed += len;
+        result.map(|()| counts)
     }
 
     /// Finalizes validation and checks for incomplete sequences.
     ///
     /// This method must be called after processing all input data to ensure
     /// that no incomplete multibyte UTF-8 sequences remain. Any incomplete
-    /// sequence at the end of the input is considered an error.
+    /// sequence at the end of the input is considered an error. Bytes buffered
+    /// by [`update`](Self::update) are validated first.
     ///
     /// # Examples
     ///
@@ -91,68 +399,130 @@ impl Utf8Validator {
     /// ```
     #[inline]
     pub fn finish(&mut self) -> crate::Result<()> {
+        // flush the partial chunk, zero padding is fine at the end of the input
+        if self.buffered > 0 {
+            let len = core::mem::take(&mut self.buffered);
+            let chunk = self.buffer;
+            self.process_slice(&chunk[..len])?;
+        }
+
         // any incomplete sequences at the end of input are errors
         self.error |= self.incomplete;
-        self.check_error()
+        self.check_error(self.previous, &[])
     }
 
     /// Validates a 64-byte chunk containing non-ASCII data.
-    #[inline]
-    fn validate_utf8(&mut self, data: &Simd<u8, 64>) -> crate::Result<()> {
-        let ptr = data.as_array().as_ptr();
-
-        // split 64 byte chunk into four 16-byte SIMD vectors with minimal data movement
-        let chunks = unsafe {
-            [
-                Simd8x16::from(Simd::from_slice(core::slice::from_raw_parts(ptr, 16))),
-                Simd8x16::from(Simd::from_slice(core::slice::from_raw_parts(ptr.add(16), 16))),
-                Simd8x16::from(Simd::from_slice(core::slice::from_raw_parts(ptr.add(32), 16))),
-                Simd8x16::from(Simd::from_slice(core::slice::from_raw_parts(ptr.add(48), 16))),
-            ]
-        };
-
-        let previous = self.previous;
-
-        // validate the chunks
-        self.validate_utf8_chunk(chunks[0], previous);
-        self.validate_utf8_chunk(chunks[1], chunks[0]);
-        self.validate_utf8_chunk(chunks[2], chunks[1]);
-        self.validate_utf8_chunk(chunks[3], chunks[2]);
+    ///
+    /// The bytes selected by `COUNT` are counted from the same loads and returned.
+    #[inline(always)]
+    fn validate_utf8<V: Vector, const COUNT: u8>(&mut self, data: &[u8; 64]) -> Counts {
+        let ptr = data.as_ptr();
+
+        let mut previous = V::from_last(self.previous);
+        let mut error = V::default();
+        let mut counts = Counts::default();
+
+        // split 64 byte chunk into SIMD vectors with minimal data movement, the loop is unrolled
+        for i in 0..64 / V::LANES {
+            let chunk = unsafe { V::load(ptr.add(i * V::LANES)) };
+            error |= validate_utf8_chunk(chunk, previous);
+            if COUNT != COUNT_NONE {
+                counts.continuations += chunk.count_continuations();
+            }
+            if COUNT == COUNT_UTF16 {
+                counts.four_byte_leads += chunk.count_four_byte_leads();
+            }
+            previous = chunk;
+        }
 
         // update validator state for the next chunk
-        self.incomplete = is_incomplete(chunks[3]);
-        self.previous = chunks[3];
+        self.error |= error.fold();
+        self.incomplete = is_incomplete(previous.last());
+        self.previous = previous.last();
 
-        self.check_error()
-    }
-
-    /// Validates a single 16-byte chunk using the UTF-8 state machine.
-    #[inline]
-    fn validate_utf8_chunk(&mut self, data: Simd8x16, previous: Simd8x16) {
-        let prev1 = data.prev::<1>(previous);
-        let sc = special_cases(data, prev1);
-        self.error |= multibyte_lengths(data, previous, sc);
+        counts
     }
 
     /// Checks if any validation errors have been accumulated.
-    #[inline]
-    fn check_error(&self) -> crate::Result<()> {
-        if core::intrinsics::unlikely(self.error.value().reduce_or() != 0) {
-            Err(Utf8Error)
+    ///
+    /// The first error is located within `data` and the end of the `previous`
+    /// chunk, and reported again by all later calls.
+    #[inline(always)]
+    fn check_error(&mut self, previous: Simd8x16, data: &[u8]) -> crate::Result<()> {
+        if unlikely(!self.error.is_zero()) {
+            Err(*self.failure.get_or_insert_with(|| locate_error(previous, data, self.processed)))
         } else {
             Ok(())
         }
     }
 }
 
-/// Fast ASCII detection for 64-byte chunks.
+/// Validates a single vector using the UTF-8 state machine.
+#[inline(always)]
+fn validate_utf8_chunk<V: Vector>(data: V, previous: V) -> V {
+    let prev1 = data.prev::<1>(previous);
+    let sc = special_cases(data, prev1);
+    multibyte_lengths(data, previous, sc)
+}
+
+/// Locates the first error in a chunk processed after `processed` bytes.
+#[cold]
+fn locate_error(previous: Simd8x16, data: &[u8], processed: usize) -> Utf8Error {
+    let (window, tail) = window(previous, data, processed);
+    utf8::locate_error(&window[..tail + data.len()], tail).rebase(processed - tail)
+}
+
+/// Copies the end of the `previous` chunk, followed by `data`, for scalar checks.
+///
+/// An invalid or incomplete sequence may start in the last 3 bytes of the
+/// previous chunk, which are returned as the number of bytes before `data`.
 #[inline]
-fn is_ascii(data: &Simd<u8, 64>) -> bool {
-    (data.reduce_or() & 0x80) == 0
+fn window(previous: Simd8x16, data: &[u8], processed: usize) -> ([u8; 3 + 64], usize) {
+    let tail = processed.min(3);
+    let mut window = [0u8; 3 + 64];
+    window[..tail].copy_from_slice(&previous.to_array()[16 - tail..]);
+    window[tail..tail + data.len()].copy_from_slice(data);
+    (window, tail)
+}
+
+/// Hints that `b` is likely true, which is only passed on to the compiler on nightly.
+#[inline(always)]
+fn likely(b: bool) -> bool {
+    #[cfg(feature = "nightly")]
+    return core::intrinsics::likely(b);
+
+    #[cfg(not(feature = "nightly"))]
+    b
+}
+
+/// Hints that `b` is likely false, which is only passed on to the compiler on nightly.
+#[inline(always)]
+fn unlikely(b: bool) -> bool {
+    #[cfg(feature = "nightly")]
+    return core::intrinsics::unlikely(b);
+
+    #[cfg(not(feature = "nightly"))]
+    b
+}
+
+/// Fast ASCII detection for 64-byte chunks, split into vectors of type `V`.
+///
+/// Loads the same vectors as the validation, so that the compiler does not
+/// split one 64-byte load into scalar parts.
+#[inline(always)]
+pub(crate) fn is_ascii<V: Vector>(data: &[u8; 64]) -> bool {
+    let ptr = data.as_ptr();
+
+    let mut any = V::default();
+    for i in 0..64 / V::LANES {
+        any |= unsafe { V::load(ptr.add(i * V::LANES)) };
+    }
+
+    any.is_ascii()
 }
 
 /// Detects incomplete multibyte sequences at the end of a chunk.
-#[inline]
+#[inline(always)]
 fn is_incomplete(data: Simd8x16) -> Simd8x16 {
     // Check the last 4 bytes for UTF-8 lead bytes that would require continuation
     // bytes: 0xC0-0xDF (2-byte), 0xE0-0xEF (3-byte), 0xF0-0xF7 (4-byte)
@@ -165,8 +535,8 @@ fn is_incomplete(data: Simd8x16) -> Simd8x16 {
 }
 
 /// Identifies special UTF-8 validation cases using lookup tables.
-#[inline]
-fn special_cases(data: Simd8x16, previous: Simd8x16) -> Simd8x16 {
+#[inline(always)]
+fn special_cases<V: Vector>(data: V, previous: V) -> V {
     // Bit 0 = Too Short (lead byte/ASCII followed by lead byte/ASCII)
     // Bit 1 = Too Long (ASCII followed by continuation)
     // Bit 2 = Overlong 3-byte
@@ -213,7 +583,7 @@ fn special_cases(data: Simd8x16, previous: Simd8x16) -> Simd8x16 {
 
     const CARRY: u8 = TOO_SHORT | TOO_LONG | TWO_CONTS; // These all have ____ in byte 1
 
-    let byte_1_low = (previous & Simd8x16::from(0x0f)).lookup_16(
+    let byte_1_low = (previous & V::from(0x0f)).lookup_16(
         Simd8x16::new(
             // ____0000 ________
             CARRY | OVERLONG_3 | OVERLONG_2 | OVERLONG_4,
@@ -267,20 +637,20 @@ fn special_cases(data: Simd8x16, previous: Simd8x16) -> Simd8x16 {
 }
 
 /// Validates multibyte UTF-8 sequence lengths.
-#[inline]
-fn multibyte_lengths(data: Simd8x16, previous: Simd8x16, special_cases: Simd8x16) -> Simd8x16 {
+#[inline(always)]
+fn multibyte_lengths<V: Vector>(data: V, previous: V, special_cases: V) -> V {
     let prev2 = data.prev::<2>(previous);
     let prev3 = data.prev::<3>(previous);
     let must23 = must_be_2_3_continuation(prev2, prev3);
-    let must23_80 = must23 & Simd8x16::from(0x80);
+    let must23_80 = must23 & V::from(0x80);
     must23_80 ^ special_cases
 }
 
 /// Determines which positions must be continuation bytes for 3 and 4-byte sequences.
-#[inline]
-fn must_be_2_3_continuation(previous2: Simd8x16, previous3: Simd8x16) -> Simd8x16 {
-    let is_third_byte  = previous2.saturating_sub(Simd8x16::from(0xe0-0x80)); // Only 111_____ will be >= 0x80
-    let is_fourth_byte = previous3.saturating_sub(Simd8x16::from(0xf0-0x80)); // Only 1111____ will be >= 0x80
+#[inline(always)]
+fn must_be_2_3_continuation<V: Vector>(previous2: V, previous3: V) -> V {
+    let is_third_byte  = previous2.saturating_sub(V::from(0xe0-0x80)); // Only 111_____ will be >= 0x80
+    let is_fourth_byte = previous3.saturating_sub(V::from(0xf0-0x80)); // Only 1111____ will be >= 0x80
     is_third_byte | is_fourth_byte
 }
 
@@ -290,11 +660,11 @@ mod tests {
 
     #[test]
     fn test_is_ascii() {
-        let simd: Simd<u8, 64> = Simd::from_slice("832,qqq\n123,aaa\n456,bbb\n666,ccc\n321,qqq\n394,ddd\n123,ask\n291,aew\n".as_bytes());
-        assert!(is_ascii(&simd));
+        let simd: &[u8; 64] = "832,qqq\n123,aaa\n456,bbb\n666,ccc\n321,qqq\n394,ddd\n123,ask\n291,aew\n".as_bytes().try_into().unwrap();
+        assert!(is_ascii::<Simd8x16>(simd));
 
-        let simd: Simd<u8, 64> = Simd::from_slice("832,qqq\n😀234\n456,bbb\n666,ccc\n321,qqq\n394,ddd\n123,ask\n291,aew\n".as_bytes());
-        assert!(!is_ascii(&simd));
+        let simd: &[u8; 64] = "832,qqq\n😀234\n456,bbb\n666,ccc\n321,qqq\n394,ddd\n123,ask\n291,aew\n".as_bytes().try_into().unwrap();
+        assert!(!is_ascii::<Simd8x16>(simd));
     }
 
     #[test]
@@ -311,8 +681,7 @@ mod tests {
             core::str::from_utf8(sequence).unwrap();
 
             // Valid sequence for us?
-            let simd: Simd<u8, 64> = Simd::from_slice(sequence);
-            v.next(&simd).unwrap();
+            v.next(sequence.try_into().unwrap()).unwrap();
         }
     }
 
@@ -330,8 +699,134 @@ mod tests {
             assert!(core::str::from_utf8(sequence).is_err());
 
             // Invalid sequence for us?
-            let simd: Simd<u8, 64> = Simd::from_slice(sequence);
-            assert!(v.next(&simd).is_err());
+            assert!(v.next(sequence).is_err());
+        }
+    }
+
+    #[test]
+    fn test_error_position() {
+        let mut v = Utf8Validator::new();
+
+        let ascii = [b'a'; 64];
+        v.next(&ascii).unwrap();
+
+        // truncated sequence spanning two chunks
+        let mut bytes = [b'a'; 64];
+        bytes[62] = 0xF0;
+        bytes[63] = 0x9F;
+        v.next(&bytes).unwrap();
+
+        let mut bytes = [b'a'; 64];
+        bytes[0] = 0x98;
+        let err = v.next(&bytes).unwrap_err();
+        assert_eq!(err.valid_up_to(), 126);
+        assert_eq!(err.error_len(), Some(3));
+
+        // the first error sticks
+        assert_eq!(v.next(&ascii), Err(err));
+        assert_eq!(v.finish(), Err(err));
+    }
+
+    #[test]
+    fn test_incomplete_at_end() {
+        let mut v = Utf8Validator::new();
+
+        let mut bytes = [b'a'; 64];
+        bytes[63] = 0xC3;
+        v.next(&bytes).unwrap();
+
+        let err = v.finish().unwrap_err();
+        assert_eq!(err.valid_up_to(), 63);
+        assert_eq!(err.error_len(), None);
+        assert_eq!(err.kind(), crate::Utf8ErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_update() {
+        let text = crate::fixtures::TEXT.repeat(4);
+        let bytes = text.as_bytes();
+
+        for size in [1, 3, 7, 63, 64, 65, 100] {
+            // valid input split at arbitrary positions
+            let mut v = Utf8Validator::new();
+            for chunk in bytes.chunks(size) {
+                v.update(chunk).unwrap();
+            }
+            v.finish().unwrap();
+
+            // invalid input reports the same error as from_utf8
+            for position in [0, 9, 70, bytes.len() - 1] {
+                let mut invalid = [0u8; 512];
+                let invalid = &mut invalid[..bytes.len()];
+                invalid.copy_from_slice(bytes);
+                invalid[position] = 0xF0;
+
+                let mut v = Utf8Validator::new();
+                let err = invalid.chunks(size).try_for_each(|chunk| v.update(chunk)).and_then(|_| v.finish());
+                assert_eq!(err, Err(crate::from_utf8(invalid).unwrap_err()));
+            }
         }
     }
+
+    #[test]
+    fn test_next_after_update() {
+        let text = crate::fixtures::TEXT.repeat(4);
+        let bytes = text.as_bytes();
+
+        // a partial chunk from update, followed by whole chunks that are validated after it
+        for split in [0, 1, 10, 63] {
+            let mut v = Utf8Validator::new();
+            v.update(&bytes[..split]).unwrap();
+            for chunk in bytes[split..].chunks_exact(64) {
+                v.next(chunk.try_into().unwrap()).unwrap();
+            }
+            v.update(bytes[split..].chunks_exact(64).remainder()).unwrap();
+            v.finish().unwrap();
+
+            let mut invalid = [0u8; 512];
+            let invalid = &mut invalid[..bytes.len()];
+            invalid.copy_from_slice(bytes);
+            invalid[100] = 0xFF;
+
+            let mut v = Utf8Validator::new();
+            v.update(&invalid[..split]).unwrap();
+            let err = invalid[split..].chunks_exact(64).try_for_each(|chunk| v.next(chunk.try_into().unwrap()));
+            assert_eq!(err, Err(crate::from_utf8(invalid).unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn test_check_partial() {
+        let text = crate::fixtures::TEXT.repeat(4);
+        let bytes = text.as_bytes();
+
+        for size in [1, 2, 5, 64, 70] {
+            let mut v = Utf8Validator::new();
+            let mut fed = 0;
+
+            for chunk in bytes.chunks(size) {
+                v.update(chunk).unwrap();
+                fed += chunk.len();
+
+                let expected = match core::str::from_utf8(&bytes[..fed]) {
+                    Ok(_) => 0,
+                    Err(err) => fed - err.valid_up_to(),
+                };
+                assert_eq!(v.check_partial(), Ok(expected));
+            }
+
+            v.finish().unwrap();
+        }
+
+        // an error within the buffered bytes
+        let mut v = Utf8Validator::new();
+        v.update(&[b'a'; 100]).unwrap();
+        v.update(b"\xE2\x82a").unwrap();
+
+        let err = v.check_partial().unwrap_err();
+        assert_eq!(err.valid_up_to(), 100);
+        assert_eq!(err.error_len(), Some(2));
+        assert_eq!(v.update(b"b"), Err(err));
+        assert_eq!(v.finish(), Err(err));
+    }
 }